@@ -0,0 +1,392 @@
+//! Ways of embedding a config into the crate being built.
+//!
+//! [`Sealed`] and [`Masked`] embed the config itself, [`Signed`] its signature, and [`Provenance`]
+//! records which config was embedded. [`Sealed`] and [`Masked`] support `strip_paths`. Code pulled
+//! in with `include!` records the OUT_DIR path in the debug info of the binary, raw bytes pulled in
+//! with `include_bytes!` do not, so with `strip_paths` set only raw bytes are written.
+
+use crate::{instruction, out_dir, Loaded, SIGNED_CFG};
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305,
+};
+use serde::Serialize;
+
+use std::fmt::Write;
+use std::io;
+
+/// A way of writing a config into `OUT_DIR` for the crate being built to include.
+pub trait Emitter<T> {
+    fn emit(&self, config: &Loaded<T>) -> io::Result<()>;
+}
+
+const SEALED_PREAMBLE: &str = r#"
+/// Generated Value for configuration hardcoded at compile time. The config is sealed using
+/// ChaCha20-Poly1305 under a key generated for this build only and the key is stored split
+/// into two shares which have to be XOR'd together before the config can be opened.
+"#;
+
+/// Embed the config file as written, sealed with ChaCha20-Poly1305 under a key drawn fresh for
+/// every build. The key is stored as two shares which XOR to the real key.
+///
+/// Writes `obfuscated.rs` defining `BOOTSTRAP_CONFIG_SEALED`, `BOOTSTRAP_CONFIG_NONCE`,
+/// `BOOTSTRAP_KEY_SHARE_A`, and `BOOTSTRAP_KEY_SHARE_B`, or with `strip_paths` the files
+/// `config.sealed`, `config.nonce`, `key.share_a`, and `key.share_b` holding the same bytes.
+#[derive(Debug, Default, Clone)]
+pub struct Sealed {
+    strip_paths: bool,
+}
+
+impl Sealed {
+    pub fn strip_paths(mut self, strip_paths: bool) -> Self {
+        self.strip_paths = strip_paths;
+        self
+    }
+
+    /// Embed an empty ciphertext, telling the crate that no config was provided at all.
+    pub fn emit_empty(&self) -> io::Result<()> {
+        self.write(None)
+    }
+
+    fn write(&self, plaintext: Option<&[u8]>) -> io::Result<()> {
+        // the key only lives for the duration of this build, a fresh one is drawn every time
+        // the config is (re-)embedded.
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let share_a = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let share_b: Vec<u8> = key.iter().zip(share_a.iter()).map(|(k, a)| k ^ a).collect();
+
+        let ciphertext = match plaintext {
+            Some(plaintext) => ChaCha20Poly1305::new(&key)
+                .encrypt(&nonce, plaintext)
+                .map_err(|_| io::Error::other("failed to seal bootstrap config"))?,
+            None => Vec::new(),
+        };
+
+        let out_dir = out_dir();
+        if self.strip_paths {
+            std::fs::write(out_dir.join("config.sealed"), &ciphertext)?;
+            std::fs::write(out_dir.join("config.nonce"), nonce)?;
+            std::fs::write(out_dir.join("key.share_a"), share_a)?;
+            std::fs::write(out_dir.join("key.share_b"), share_b)?;
+        } else {
+            // creating a string with the sealed config and the key shares
+            let mut array_string = String::from(SEALED_PREAMBLE);
+            write_bytes(&mut array_string, "BOOTSTRAP_CONFIG_SEALED", &ciphertext);
+            write_bytes(&mut array_string, "BOOTSTRAP_CONFIG_NONCE", &nonce);
+            write_bytes(&mut array_string, "BOOTSTRAP_KEY_SHARE_A", &share_a);
+            write_bytes(&mut array_string, "BOOTSTRAP_KEY_SHARE_B", &share_b);
+
+            std::fs::write(out_dir.join("obfuscated.rs"), array_string)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> Emitter<T> for Sealed {
+    fn emit(&self, config: &Loaded<T>) -> io::Result<()> {
+        self.write(Some(config.contents().as_bytes()))
+    }
+}
+
+/// Write a `static` byte slice with the given name. Statics (unlike consts) are not inlined
+/// at their use sites which keeps the shares from being folded back into a single key.
+fn write_bytes(out: &mut String, name: &str, bytes: &[u8]) {
+    write!(out, "pub(crate) static {name}: &[u8] = &[").unwrap();
+    for b in bytes {
+        write!(out, "{b:#04x},").unwrap();
+    }
+    out.push_str("];\r\n");
+}
+
+/// Embed the config as a rust expression constructing it, with every string replaced by a masked
+/// byte array that is only rebuilt into a `String` at runtime by `config_types::unmask`, which
+/// the crate has to have in scope as `unmask`.
+///
+/// The expression builds the `toml::Value` tree of the config and deserializes it back into the
+/// config type, so it never has to name the type or any of the types nested in it and works for
+/// any config that round trips through toml. The type is inferred from where the expression is
+/// used and the crate has to depend on `toml` itself.
+///
+/// The expression replaces `{config}` in the template, which is written to `default.rs`. With
+/// `strip_paths` the config is instead serialized back to toml and written as a single masked
+/// blob to `config.masked`, with the mask in `config.mask`. This gives up the field-level
+/// masking, but the config is still never stored contiguously in the binary.
+#[derive(Debug, Clone)]
+pub struct Masked {
+    template: String,
+    strip_paths: bool,
+}
+
+impl Masked {
+    pub fn new(template: &str) -> Self {
+        Self {
+            template: template.to_string(),
+            strip_paths: false,
+        }
+    }
+
+    pub fn strip_paths(mut self, strip_paths: bool) -> Self {
+        self.strip_paths = strip_paths;
+        self
+    }
+}
+
+impl<T: Serialize> Emitter<T> for Masked {
+    fn emit(&self, config: &Loaded<T>) -> io::Result<()> {
+        let out_dir = out_dir();
+
+        if self.strip_paths {
+            let config_str = toml::to_string(config.config()).map_err(io::Error::other)?;
+            let (masked, mask) = mask(config_str.as_bytes());
+
+            std::fs::write(out_dir.join("config.masked"), masked)?;
+            std::fs::write(out_dir.join("config.mask"), mask)?;
+        } else {
+            let value = toml::Value::try_from(config.config()).map_err(io::Error::other)?;
+            let code = self.template.replace("{config}", &config_expr(&value));
+
+            std::fs::write(out_dir.join("default.rs"), code)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// XOR `bytes` with a freshly drawn random mask, returning the masked bytes and the mask.
+fn mask(bytes: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut mask = vec![0u8; bytes.len()];
+    getrandom::getrandom(&mut mask).unwrap();
+    let masked = bytes.iter().zip(&mask).map(|(b, m)| b ^ m).collect();
+    (masked, mask)
+}
+
+/// An expression deserializing `value` into whatever type the context asks for, with every string
+/// masked as described for [`Masked`]. `toml` and `unmask` have to be in scope where it is used.
+pub fn config_expr(value: &toml::Value) -> String {
+    let mut out = String::from("toml::Value::try_into(");
+    value_expr(&mut out, value);
+    out.push_str(").expect(\"embedded config does not match the config type\")");
+    out
+}
+
+/// Write an expression constructing `value` where every string, including the keys of tables, is
+/// a call to `unmask(&[..], &[..])` with a random mask drawn separately for every string.
+fn value_expr(out: &mut String, value: &toml::Value) {
+    match value {
+        toml::Value::String(s) => {
+            out.push_str("toml::Value::String(");
+            string_expr(out, s);
+            out.push(')');
+        }
+        toml::Value::Integer(i) => write!(out, "toml::Value::Integer({i})").unwrap(),
+        // written as bits so that the value, NaN and infinities included, round trips exactly.
+        toml::Value::Float(f) => write!(
+            out,
+            "toml::Value::Float(f64::from_bits({:#x}))",
+            f.to_bits()
+        )
+        .unwrap(),
+        toml::Value::Boolean(b) => write!(out, "toml::Value::Boolean({b})").unwrap(),
+        toml::Value::Datetime(d) => {
+            out.push_str("toml::Value::Datetime(");
+            string_expr(out, &d.to_string());
+            out.push_str(".parse().unwrap())");
+        }
+        toml::Value::Array(values) => {
+            out.push_str("toml::Value::Array(vec![");
+            for value in values {
+                value_expr(out, value);
+                out.push(',');
+            }
+            out.push_str("])");
+        }
+        toml::Value::Table(table) => {
+            out.push_str("toml::Value::Table(toml::map::Map::from_iter([");
+            for (key, value) in table {
+                out.push('(');
+                string_expr(out, key);
+                out.push(',');
+                value_expr(out, value);
+                out.push_str("),");
+            }
+            out.push_str("]))");
+        }
+    }
+}
+
+fn string_expr(out: &mut String, s: &str) {
+    let (masked, mask) = mask(s.as_bytes());
+    write!(out, "unmask(&{masked:?}, &{mask:?})").unwrap();
+}
+
+/// Embed the detached signature of the config, see [`Loaded::verify_signature`], so that the
+/// crate can verify the config again at runtime.
+///
+/// Writes `config.sig` holding the signature, empty if the config is not signed. For a signed
+/// config [`SIGNED_CFG`] is set on the crate being built, which has to verify the config whenever
+/// it is set rather than whenever the signature is not empty, so that patching the signature out
+/// of the binary does not turn off the check.
+#[derive(Debug, Default, Clone)]
+pub struct Signed;
+
+impl Signed {
+    /// Record that no signed config was embedded.
+    pub fn emit_empty(&self) -> io::Result<()> {
+        self.write(None)
+    }
+
+    fn write(&self, signature: Option<&[u8]>) -> io::Result<()> {
+        std::fs::write(out_dir().join("config.sig"), signature.unwrap_or_default())?;
+        if signature.is_some() {
+            instruction(&format!("cargo:rustc-cfg={SIGNED_CFG}"));
+        }
+        Ok(())
+    }
+}
+
+impl<T> Emitter<T> for Signed {
+    fn emit(&self, config: &Loaded<T>) -> io::Result<()> {
+        self.write(config.signature())
+    }
+}
+
+/// Name of the environment variable set for the crate being built holding the SHA-256 of the
+/// embedded config, empty if no config was embedded.
+pub const CONFIG_SHA256_ENV: &str = "BOOTSTRAP_CONFIG_SHA256";
+
+/// Name of the environment variable set for the crate being built holding its enabled features,
+/// separated by commas.
+pub const FEATURES_ENV: &str = "BOOTSTRAP_FEATURES";
+
+/// Record which config went into a build, for auditing releases.
+///
+/// Writes `provenance.json` to `OUT_DIR`, and a copy named `{package}-provenance.json` next to
+/// the build artifacts in `target/<profile>/`. It holds the package name and version, its enabled
+/// features, and where the config came from along with the SHA-256 of the config in canonical
+/// form (see [`config_types::canonical_toml`]), so that formatting and comments do not change the
+/// hash. The hash and the features are also passed to the crate being built in
+/// [`CONFIG_SHA256_ENV`] and [`FEATURES_ENV`], so that it can report them at runtime.
+#[derive(Debug, Default, Clone)]
+pub struct Provenance;
+
+#[derive(Serialize)]
+struct ProvenanceRecord {
+    package: String,
+    version: String,
+    features: Vec<String>,
+    config: Option<ConfigRecord>,
+}
+
+#[derive(Serialize)]
+struct ConfigRecord {
+    path: Option<String>,
+    source: String,
+    sha256: String,
+}
+
+impl Provenance {
+    /// Record that no config was embedded.
+    pub fn emit_empty(&self) -> io::Result<()> {
+        self.write(None)
+    }
+
+    fn write(&self, config: Option<ConfigRecord>) -> io::Result<()> {
+        let env = |name| std::env::var(name).map_err(io::Error::other);
+
+        // cargo sets CARGO_FEATURE_<NAME> for every enabled feature, uppercased with `-` as `_`.
+        let mut features: Vec<String> = std::env::vars()
+            .filter_map(|(name, _)| {
+                let feature = name.strip_prefix("CARGO_FEATURE_")?;
+                Some(feature.to_lowercase().replace('_', "-"))
+            })
+            .collect();
+        features.sort();
+
+        let record = ProvenanceRecord {
+            package: env("CARGO_PKG_NAME")?,
+            version: env("CARGO_PKG_VERSION")?,
+            features,
+            config,
+        };
+
+        let sha256 = record.config.as_ref().map_or("", |config| &config.sha256);
+        instruction(&format!("cargo:rustc-env={CONFIG_SHA256_ENV}={sha256}"));
+        instruction(&format!(
+            "cargo:rustc-env={FEATURES_ENV}={}",
+            record.features.join(",")
+        ));
+
+        let json = serde_json::to_string_pretty(&record).map_err(io::Error::other)?;
+        let out_dir = out_dir();
+        std::fs::write(out_dir.join("provenance.json"), &json)?;
+
+        // OUT_DIR is target/<profile>/build/<package>-<hash>/out
+        if let Some(profile_dir) = out_dir.ancestors().nth(3) {
+            let file_name = format!("{}-provenance.json", record.package);
+            std::fs::write(profile_dir.join(file_name), &json)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Serialize> Emitter<T> for Provenance {
+    fn emit(&self, config: &Loaded<T>) -> io::Result<()> {
+        let (path, source) = match config.located() {
+            Some(located) => (
+                Some(located.path().display().to_string()),
+                located.source().to_string(),
+            ),
+            None => (None, "in-source default".to_string()),
+        };
+
+        self.write(Some(ConfigRecord {
+            path,
+            source,
+            sha256: config_types::canonical_sha256(config.config()).map_err(io::Error::other)?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Outer {
+        name: String,
+        inner: Inner,
+        missing: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    struct Inner {
+        values: Vec<u16>,
+        ratio: f64,
+    }
+
+    #[test]
+    fn generated_code_is_type_agnostic() {
+        let config = Outer {
+            name: "secret-name".to_string(),
+            inner: Inner {
+                values: vec![1, 2],
+                ratio: 0.5,
+            },
+            missing: None,
+        };
+        let code = config_expr(&toml::Value::try_from(&config).unwrap());
+
+        assert!(!code.contains("Outer"));
+        assert!(!code.contains("Inner"));
+        assert!(!code.contains("secret-name"));
+        assert!(!code.contains("values"));
+        assert!(code.contains("toml::Value::Integer(2)"));
+        assert!(code.contains(&format!("{:#x}", 0.5f64.to_bits())));
+        // quotes only appear in the message of the `expect`
+        assert_eq!(code.matches('"').count(), 2);
+    }
+}
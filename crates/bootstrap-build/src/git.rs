@@ -0,0 +1,133 @@
+//! Checks that a config file cannot end up in the git repository it lives in.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The result of checking a config file against the enclosing git repository, if any.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GitCheck {
+    /// Why the file is at risk of being committed, `None` if it is not.
+    pub(crate) problem: Option<String>,
+    /// Files whose changes can change the outcome of the check.
+    pub(crate) dependencies: Vec<PathBuf>,
+}
+
+/// Check whether `path` is tracked by git, or would be picked up by `git add` because it is not
+/// ignored. Missing files, files outside of a git work tree, or checks on a host without git,
+/// pass.
+pub(crate) fn check(path: &Path) -> GitCheck {
+    let (Some(dir), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return GitCheck::default();
+    };
+    if !path.is_file() {
+        return GitCheck::default();
+    }
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .output()
+            .ok()
+    };
+
+    let Some(toplevel) = git(&["rev-parse", "--show-toplevel"])
+        .filter(|output| output.status.success())
+        .map(|output| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+    else {
+        return GitCheck::default();
+    };
+
+    // `git add` and `git rm` change the index, edits to the ignore rules change .gitignore. Only
+    // existing .gitignore files are tracked, cargo reruns the build every time for missing ones.
+    let mut dependencies = Vec::new();
+    if let Some(output) = git(&["rev-parse", "--path-format=absolute", "--git-path", "index"])
+        .filter(|output| output.status.success())
+    {
+        dependencies.push(PathBuf::from(
+            String::from_utf8_lossy(&output.stdout).trim(),
+        ));
+    }
+    dependencies.extend(
+        dir.ancestors()
+            .take_while(|ancestor| ancestor.starts_with(&toplevel))
+            .map(|ancestor| ancestor.join(".gitignore"))
+            .filter(|gitignore| gitignore.is_file()),
+    );
+
+    let file_name = Path::new(file_name);
+    let succeeds = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .arg(file_name)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+
+    let problem = if succeeds(&["ls-files", "--error-unmatch", "--"]) {
+        Some(format!(
+            "bootstrap config {} is tracked by git, secrets must never be committed; remove it \
+            with `git rm --cached` and add it to .gitignore",
+            path.display()
+        ))
+    } else if !succeeds(&["check-ignore", "-q", "--"]) {
+        Some(format!(
+            "bootstrap config {} is not ignored by git; add it to .gitignore so that it cannot be \
+            committed by accident",
+            path.display()
+        ))
+    } else {
+        None
+    };
+
+    GitCheck {
+        problem,
+        dependencies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracked_and_ignored() {
+        let dir = std::env::temp_dir().join(format!("bootstrap-git-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(&dir)
+                .args(args)
+                .output()
+                .is_ok_and(|output| output.status.success())
+        };
+
+        let config = dir.join("config.toml");
+        std::fs::write(&config, "").unwrap();
+        // nothing to check outside of a repository
+        assert!(check(&config).problem.is_none());
+
+        if !git(&["init", "-q"]) {
+            // no git on this host
+            std::fs::remove_dir_all(&dir).unwrap();
+            return;
+        }
+
+        let problem = check(&config).problem.unwrap();
+        assert!(problem.contains("is not ignored by git"), "{problem}");
+
+        std::fs::write(dir.join(".gitignore"), "config.toml\n").unwrap();
+        let checked = check(&config);
+        assert!(checked.problem.is_none());
+        assert!(checked.dependencies.contains(&dir.join(".gitignore")));
+
+        assert!(git(&["add", "-f", "config.toml"]));
+        let problem = check(&config).problem.unwrap();
+        assert!(problem.contains("is tracked by git"), "{problem}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
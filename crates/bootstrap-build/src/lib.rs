@@ -0,0 +1,1051 @@
+//! Build script support for crates that bake a config file into the binary at compile time.
+//!
+//! Discovery of the config file, validation against the config type, rebuild tracking, and
+//! emission of the code / data the crate includes are handled here so that a bootstrap strategy
+//! only has to pick an [`Emitter`].
+//!
+//! ```no_run
+//! use bootstrap_build::{emit::Sealed, Bootstrap};
+//! # #[derive(serde::Deserialize)]
+//! # struct BaseConfig {}
+//!
+//! fn main() {
+//!     bootstrap_build::init();
+//!
+//!     let emitter = Sealed::default();
+//!     let result = Bootstrap::new("NYMVPN")
+//!         .locate()
+//!         .and_then(|config| config.load::<BaseConfig>())
+//!         .and_then(|config| config.emit(&emitter));
+//!
+//!     if let Err(diagnostic) = result {
+//!         diagnostic.report();
+//!         emitter.emit_empty().unwrap();
+//!     }
+//! }
+//! ```
+
+pub mod emit;
+mod git;
+pub mod search;
+
+pub use emit::Emitter;
+pub use search::SearchDir;
+
+use search::Dependencies;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::cell::RefCell;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Name of the cfg set on the crate being built when a [`Diagnostic`] has been reported.
+pub const ERROR_CFG: &str = "bootstrap_config_error";
+
+/// Name of the file in `OUT_DIR` holding the `compile_error!` for a reported [`Diagnostic`].
+pub const DIAGNOSTICS_FILE: &str = "diagnostics.rs";
+
+/// Name of the cfg set on the crate being built when a signed config was embedded, see
+/// [`emit::Signed`].
+pub const SIGNED_CFG: &str = "bootstrap_config_signed";
+
+/// Name of the environment variable set for the crate being built holding the hex encoded public
+/// key configs are verified against, see [`Bootstrap::verifying_key`].
+pub const VERIFYING_KEY_ENV: &str = "BOOTSTRAP_CONFIG_PUBKEY";
+
+/// Print a cargo warning from a build script, one per line of the message.
+#[macro_export]
+macro_rules! warn {
+    ($($tokens: tt)*) => {
+        for line in format!($($tokens)*).lines() {
+            $crate::instruction(&format!("cargo:warning={line}"));
+        }
+    }
+}
+
+thread_local! {
+    /// Set while inside [`capture`].
+    static CAPTURE: RefCell<Option<Capture>> = const { RefCell::new(None) };
+}
+
+struct Capture {
+    release: bool,
+    instructions: Vec<String>,
+}
+
+/// Print an instruction for cargo, e.g. `cargo:warning=...`, or record it when inside
+/// [`capture`]. Every instruction this crate gives goes through here.
+#[doc(hidden)]
+pub fn instruction(line: &str) {
+    let captured = CAPTURE.with_borrow_mut(|capture| match capture {
+        Some(capture) => {
+            capture.instructions.push(line.to_string());
+            true
+        }
+        None => false,
+    });
+    if !captured {
+        println!("{line}");
+    }
+}
+
+/// Run `f` like a build script would, as a release build if `release` is set, and return the
+/// instructions for cargo it gave instead of printing them. For use outside of build scripts,
+/// e.g. in proc-macros, which cannot give cargo instructions and are not told the profile.
+pub fn capture<R>(release: bool, f: impl FnOnce() -> R) -> (R, Vec<String>) {
+    CAPTURE.set(Some(Capture {
+        release,
+        instructions: Vec::new(),
+    }));
+    let result = f();
+    let capture = CAPTURE.take().expect("capture is not nested");
+    (result, capture.instructions)
+}
+
+/// Declarations every build script using this crate has to make, regardless of whether a config
+/// ends up being embedded.
+pub fn init() {
+    instruction(&format!("cargo:rustc-check-cfg=cfg({ERROR_CFG})"));
+    instruction(&format!("cargo:rustc-check-cfg=cfg({SIGNED_CFG})"));
+    instruction("cargo:rerun-if-changed=build.rs");
+}
+
+/// The `OUT_DIR` of the crate being built. This environment variable is defined by cargo.
+pub fn out_dir() -> PathBuf {
+    PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo for build scripts"))
+}
+
+/// Describes where the config file for a bootstrap strategy is found.
+#[derive(Debug, Clone)]
+pub struct Bootstrap {
+    env_var: String,
+    legacy_env_vars: Vec<String>,
+    default_file: String,
+    pubkey_env_var: String,
+    search: Vec<SearchDir>,
+    deny_tracked: bool,
+}
+
+impl Bootstrap {
+    /// Bootstrap from the file named by `{prefix}_CONFIG_PATH`, or if the variable is not set
+    /// from `{prefix}-config.toml` (lowercase) in the workspace root, the crate directory, or
+    /// `$XDG_CONFIG_HOME/{prefix}/` (lowercase), whichever is found first.
+    pub fn new(prefix: &str) -> Self {
+        Self::from_env(
+            &format!("{prefix}_CONFIG_PATH"),
+            &format!("{}-config.toml", prefix.to_lowercase()),
+        )
+        .search_dir(SearchDir::XdgConfig(prefix.to_lowercase()))
+    }
+
+    /// Bootstrap from the file named by `env_var`, or if the variable is not set from
+    /// `default_file` in the workspace root or the crate directory, whichever is found first.
+    ///
+    /// The key configs are signed with is read from `env_var` with its `_PATH` suffix replaced by
+    /// `_PUBKEY`, e.g. `NYMVPN_CONFIG_PUBKEY`, see [`Bootstrap::verifying_key`].
+    pub fn from_env(env_var: &str, default_file: &str) -> Self {
+        Self {
+            env_var: env_var.to_string(),
+            legacy_env_vars: Vec::new(),
+            default_file: default_file.to_string(),
+            pubkey_env_var: format!(
+                "{}_PUBKEY",
+                env_var.strip_suffix("_PATH").unwrap_or(env_var)
+            ),
+            search: vec![SearchDir::WorkspaceRoot, SearchDir::CrateDir],
+            deny_tracked: false,
+        }
+    }
+
+    /// Also accept the path from a deprecated environment variable, so that existing setups keep
+    /// working. The current variable takes precedence, and a warning is emitted whenever the
+    /// deprecated one is set, whether it is used or ignored.
+    pub fn legacy_env(mut self, env_var: &str) -> Self {
+        self.legacy_env_vars.push(env_var.to_string());
+        self
+    }
+
+    /// Use `file_name` when no environment variable is set.
+    pub fn default_file(mut self, file_name: &str) -> Self {
+        self.default_file = file_name.to_string();
+        self
+    }
+
+    /// Also look for the default file in `dir`, after all directories searched so far.
+    pub fn search_dir(mut self, dir: SearchDir) -> Self {
+        self.search.push(dir);
+        self
+    }
+
+    /// Fail instead of warning when the config file is tracked by git, or is inside a git work
+    /// tree without being ignored. See [`Bootstrap::locate`].
+    pub fn deny_tracked(mut self, deny: bool) -> Self {
+        self.deny_tracked = deny;
+        self
+    }
+
+    /// Name of the environment variable used to pick the config file.
+    pub fn env_var(&self) -> &str {
+        &self.env_var
+    }
+
+    /// Name of the environment variable holding the key configs are signed with.
+    pub fn pubkey_env_var(&self) -> &str {
+        &self.pubkey_env_var
+    }
+
+    /// The Ed25519 public key configs have to be signed with, see [`Loaded::verify_signature`],
+    /// given as 64 hex digits in [`Bootstrap::pubkey_env_var`]. Without the variable `dev_key` is
+    /// used, a development key whose secret half is not kept secret. Release builds refuse the
+    /// development key, whether it is used by default or set explicitly, so that only configs
+    /// signed with the release key can ship. It is only needed once there is a config to check,
+    /// so builds falling back to an in-source default do not ask for it.
+    ///
+    /// The key is passed on to the crate being built in [`VERIFYING_KEY_ENV`], so that it can
+    /// verify the config again at runtime. On failure `dev_key` is passed so that the crate still
+    /// compiles far enough for the diagnostic to be the only error reported.
+    pub fn verifying_key(&self, dev_key: &[u8; 32]) -> Result<[u8; 32], Diagnostic> {
+        instruction(&format!(
+            "cargo:rerun-if-env-changed={}",
+            self.pubkey_env_var
+        ));
+
+        let key = self.resolve_verifying_key(dev_key);
+        let hex: String = key
+            .as_ref()
+            .unwrap_or(dev_key)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        instruction(&format!("cargo:rustc-env={VERIFYING_KEY_ENV}={hex}"));
+
+        key
+    }
+
+    /// [`Bootstrap::verifying_key`] without printing any instructions for cargo, see
+    /// [`Bootstrap::resolve`].
+    pub fn resolve_verifying_key(&self, dev_key: &[u8; 32]) -> Result<[u8; 32], Diagnostic> {
+        let var = &self.pubkey_env_var;
+        let key = match std::env::var(var) {
+            Ok(hex) => config_types::verifying_key_from_hex(&hex).map_err(|e| {
+                Diagnostic::other(format!("invalid config signing key in {var}: {e}"))
+            })?,
+            Err(_) => *dev_key,
+        };
+
+        if key == *dev_key && is_release() {
+            return Err(Diagnostic::other(format!(
+                "release builds refuse configs signed with the development key, as its secret \
+                half is public; set {var} to the public half of the release key as 64 hex digits"
+            )));
+        }
+        Ok(key)
+    }
+
+    /// Resolve the path to the config file and register it for rebuild tracking, reporting the
+    /// chosen file as a cargo warning so that build logs show which config went into a build.
+    ///
+    /// If one of the environment variables is set, the file it names is used whether or not it
+    /// exists, as falling back to another file could embed a config that was not intended.
+    /// Otherwise the default file is looked for in every search directory in turn and the first
+    /// one found is used. In both cases `~` and `${VAR}` are expanded and relative paths are taken
+    /// relative to the workspace root.
+    ///
+    /// A config file that is tracked by git, or that lives in a git work tree without being
+    /// ignored, is one `git add` away from leaking its secrets. This is reported as a warning, or
+    /// as an error with [`Bootstrap::deny_tracked`].
+    ///
+    /// The build script is rerun when any of the environment variables that were read change, or
+    /// when any of the files that were checked do. Candidates that do not exist cause cargo to
+    /// rerun the build script on every build, so a config found late in the search path is
+    /// regenerated every time. toml has no way of including other files, so the config file is
+    /// the only file read.
+    pub fn locate(&self) -> Result<Located, Diagnostic> {
+        let (located, deps) = self.resolve_tracked();
+
+        for env_var in deps.env_vars() {
+            instruction(&format!("cargo:rerun-if-env-changed={env_var}"));
+        }
+        // always resolved paths, cargo takes relative paths as relative to the crate manifest
+        // rather than the workspace root.
+        for file in deps.files() {
+            instruction(&format!("cargo:rerun-if-changed={}", file.display()));
+        }
+
+        let located = located?;
+
+        // the index and .gitignore files are tracked as well so that fixing the problem (or
+        // causing one with `git add`) reruns the check.
+        let git = git::check(&located.path);
+        for file in &git.dependencies {
+            instruction(&format!("cargo:rerun-if-changed={}", file.display()));
+        }
+        if let Some(problem) = git.problem {
+            if self.deny_tracked {
+                return Err(Diagnostic::other(problem));
+            }
+            warn!("{problem}");
+        }
+
+        match &located.source {
+            Source::Env(var) if *var != self.env_var => {
+                warn!("{var} is deprecated, use {} instead", self.env_var);
+            }
+            Source::Env(_) => {
+                for legacy in &self.legacy_env_vars {
+                    if std::env::var_os(legacy).is_some() {
+                        warn!(
+                            "{legacy} is deprecated and ignored, as {} is set",
+                            self.env_var
+                        );
+                    }
+                }
+            }
+            Source::Default(_) => {}
+        }
+        warn!(
+            "using bootstrap config {} ({})",
+            located.path.display(),
+            located.source
+        );
+
+        Ok(located)
+    }
+
+    /// Locate a `BaseConfig` and run every check the bootstraps crates need on it, so that a bad
+    /// config fails the build rather than the client:
+    ///
+    /// - old layouts are upgraded with [`config_types::migrate`] and a warning for each
+    ///   migration, so that old config files keep building,
+    /// - fields the layout has no place for are handled according to `unknown_fields`, with the
+    ///   names in `required_keys` suggested for keys that are likely typos of them,
+    /// - a config without one of `required_keys` fails, as the crate cannot work without them,
+    /// - the signature is verified against [`Bootstrap::verifying_key`], with
+    ///   `config_types::DEV_CONFIG_SIGNING_KEY` as the development key. The key is only resolved
+    ///   once a config has been loaded, so builds without a config do not need one.
+    pub fn load_checked(
+        &self,
+        required_keys: &[&str],
+        unknown_fields: UnknownFields,
+    ) -> Result<Loaded<config_types::BaseConfig>, Diagnostic> {
+        self.locate()
+            .and_then(|config| {
+                config.load_migrated::<config_types::BaseConfig>(|config| {
+                    let applied = config_types::migrate(config)?;
+                    Ok(applied.iter().map(ToString::to_string).collect())
+                })
+            })
+            .and_then(|config| {
+                config.check_unknown_fields(unknown_fields, |contents| {
+                    let unknown = config_types::unknown_fields(contents, required_keys)?;
+                    Ok(unknown.iter().map(ToString::to_string).collect())
+                })
+            })
+            .and_then(|config| config.validate(|config| config.keys.require(required_keys)))
+            .and_then(|config| {
+                config.verify_signature(&self.verifying_key(&config_types::DEV_CONFIG_SIGNING_KEY)?)
+            })
+    }
+
+    /// Resolve the path to the config file like [`Bootstrap::locate`], but without printing any
+    /// instructions for cargo. For use outside of build scripts, e.g. in proc-macros, where the
+    /// caller has to take care of rebuild tracking itself.
+    pub fn resolve(&self) -> Result<Located, Diagnostic> {
+        self.resolve_tracked().0
+    }
+
+    /// [`Bootstrap::resolve`], also returning everything that has to be tracked for rebuilds.
+    pub fn resolve_tracked(&self) -> (Result<Located, Diagnostic>, Dependencies) {
+        let mut deps = Dependencies::default();
+        let located = self.search(&mut deps);
+        (located, deps)
+    }
+
+    fn search(&self, deps: &mut Dependencies) -> Result<Located, Diagnostic> {
+        // read by workspace_root, listed here so that it is tracked even when it is not set.
+        deps.env_var("CARGO_WORKSPACE_DIR");
+
+        // every variable is read so that all of them are tracked, the first one set wins.
+        let values: Vec<_> = self
+            .env_vars()
+            .map(|var| (var, deps.env_var(var)))
+            .collect();
+        let from_env = values
+            .into_iter()
+            .find_map(|(var, value)| value.map(|value| (var, value)));
+
+        if let Some((var, value)) = from_env {
+            let source = Source::Env(var.clone());
+            let path = search::expand(&value, deps)
+                .and_then(search::relative_to_workspace)
+                .map_err(|e| {
+                    Diagnostic::other(format!(
+                        "cannot resolve bootstrap config `{value}` ({source}): {e}"
+                    ))
+                })?;
+            deps.file(path.clone());
+            return Ok(Located { path, source });
+        }
+
+        let source = Source::Default(self.env_var.clone());
+        let mut searched = Vec::new();
+        for dir in &self.search {
+            let dir = dir.resolve(deps).map_err(|e| {
+                Diagnostic::other(format!(
+                    "cannot resolve bootstrap config search directory ({source}): {e}"
+                ))
+            })?;
+            let Some(dir) = dir else { continue };
+
+            let path = dir.join(&self.default_file);
+            deps.file(path.clone());
+            if path.is_file() {
+                return Ok(Located { path, source });
+            }
+            searched.push(format!("  {}", path.display()));
+        }
+
+        Err(Diagnostic::not_found(format!(
+            "no bootstrap config `{}` found ({source}), searched:\n{}",
+            self.default_file,
+            searched.join("\n")
+        )))
+    }
+
+    /// All environment variables that can name the config file, in order of precedence.
+    pub fn env_vars(&self) -> impl Iterator<Item = &String> {
+        [&self.env_var].into_iter().chain(&self.legacy_env_vars)
+    }
+}
+
+/// Whether the crate is built with the release profile.
+fn is_release() -> bool {
+    CAPTURE
+        .with_borrow(|capture| capture.as_ref().map(|capture| capture.release))
+        .unwrap_or_else(|| std::env::var("PROFILE").is_ok_and(|profile| profile == "release"))
+}
+
+/// The root of the workspace the crate being built belongs to.
+///
+/// `CARGO_WORKSPACE_DIR` is used if it is set, e.g. through the `[env]` table in
+/// `.cargo/config.toml`, as cargo does not provide it. Otherwise the root is found the way cargo
+/// finds it, by walking up from `CARGO_MANIFEST_DIR` to the first manifest with a `[workspace]`
+/// table, so that crates consumed from another workspace do not depend on its cargo config.
+pub fn workspace_root() -> Result<PathBuf, String> {
+    if let Ok(workspace_dir) = std::env::var("CARGO_WORKSPACE_DIR") {
+        return Ok(PathBuf::from(workspace_dir));
+    }
+
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| "neither CARGO_WORKSPACE_DIR nor CARGO_MANIFEST_DIR is set".to_string())?;
+    Ok(find_workspace_root(Path::new(&manifest_dir)))
+}
+
+/// The directory of the first `Cargo.toml` at or above the package in `manifest_dir` that has a
+/// `[workspace]` table, or the one named by its `package.workspace` key. A package that is not
+/// part of any workspace is its own root.
+fn find_workspace_root(manifest_dir: &Path) -> PathBuf {
+    let read_manifest = |dir: &Path| -> Option<toml::Table> {
+        let contents = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+        contents.parse().ok()
+    };
+
+    let explicit = read_manifest(manifest_dir).and_then(|manifest| {
+        let workspace = manifest.get("package")?.get("workspace")?.as_str()?;
+        Some(manifest_dir.join(workspace))
+    });
+    if let Some(root) = explicit {
+        return root;
+    }
+
+    manifest_dir
+        .ancestors()
+        .find(|dir| read_manifest(dir).is_some_and(|manifest| manifest.contains_key("workspace")))
+        .unwrap_or(manifest_dir)
+        .to_path_buf()
+}
+
+/// How the config file was chosen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Named by the given environment variable.
+    Env(String),
+    /// The default file, as the given environment variable was not set.
+    Default(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Env(var) => write!(f, "set by {var}"),
+            Source::Default(var) => write!(f, "default, {var} is not set"),
+        }
+    }
+}
+
+/// A config file that has been located but not yet read.
+#[derive(Debug, Clone)]
+pub struct Located {
+    path: PathBuf,
+    source: Source,
+}
+
+impl Located {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    /// Read the config file and parse it as `T`, so that a malformed config fails the build
+    /// instead of shipping.
+    pub fn load<T: DeserializeOwned>(self) -> Result<Loaded<T>, Diagnostic> {
+        self.load_migrated(|_| Ok(Vec::new()))
+    }
+
+    /// [`Located::load`] for configs whose layout changes over time. `migrate` upgrades the parsed
+    /// file to the current layout before it is parsed as `T`, and describes each change it made.
+    /// Every change is reported as a cargo warning so that the file gets updated, the file itself
+    /// is never rewritten.
+    pub fn load_migrated<T: DeserializeOwned>(
+        self,
+        migrate: impl FnOnce(&mut toml::Table) -> Result<Vec<String>, String>,
+    ) -> Result<Loaded<T>, Diagnostic> {
+        let contents = std::fs::read_to_string(&self.path).map_err(|e| {
+            let message = format!(
+                "failed to read bootstrap config {} ({}): {e}",
+                self.path.display(),
+                self.source
+            );
+            match e.kind() {
+                std::io::ErrorKind::NotFound => Diagnostic::not_found(message),
+                _ => Diagnostic::other(message),
+            }
+        })?;
+
+        let invalid = |(line, column, e): (usize, usize, toml::de::Error)| {
+            Diagnostic::other(format!(
+                "invalid bootstrap config {}:{line}:{column} ({})\n{e}",
+                self.path.display(),
+                self.source
+            ))
+        };
+        let mut table = parse::<toml::Table>(&contents).map_err(invalid)?;
+        let migrations = migrate(&mut table).map_err(|e| {
+            Diagnostic::other(format!(
+                "invalid bootstrap config {} ({})\n{e}",
+                self.path.display(),
+                self.source
+            ))
+        })?;
+
+        // without migrations the file is parsed again, so that errors point into it
+        let config = if migrations.is_empty() {
+            parse(&contents).map_err(invalid)?
+        } else {
+            for migration in &migrations {
+                warn!(
+                    "migrated bootstrap config {} ({}) from {migration}",
+                    self.path.display(),
+                    self.source
+                );
+            }
+            warn!("update the config file to the current layout to silence these warnings");
+            toml::Value::Table(table).try_into().map_err(|e| {
+                Diagnostic::other(format!(
+                    "invalid bootstrap config {} ({}) after migrating it\n{e}",
+                    self.path.display(),
+                    self.source
+                ))
+            })?
+        };
+
+        Ok(Loaded {
+            config,
+            contents,
+            located: Some(self),
+            signature: None,
+            migrated: !migrations.is_empty(),
+        })
+    }
+}
+
+/// A config that has been read and validated, ready to be embedded.
+#[derive(Debug, Clone)]
+pub struct Loaded<T> {
+    config: T,
+    contents: String,
+    located: Option<Located>,
+    signature: Option<Vec<u8>>,
+    migrated: bool,
+}
+
+impl<T: DeserializeOwned> Loaded<T> {
+    /// A config defined in the source of the crate itself, e.g. a default used when no config
+    /// file was provided.
+    pub fn in_source(contents: &str) -> Result<Self, Diagnostic> {
+        let config = parse(contents).map_err(|(line, column, e)| {
+            Diagnostic::other(format!(
+                "invalid in-source bootstrap config at {line}:{column}\n{e}"
+            ))
+        })?;
+
+        Ok(Self {
+            config,
+            contents: contents.to_string(),
+            located: None,
+            signature: None,
+            migrated: false,
+        })
+    }
+}
+
+impl<T: Serialize> Loaded<T> {
+    /// Check the detached Ed25519 signature in `{config file}.sig`, 64 raw bytes over the
+    /// [`config_types::canonical_toml`] form of the config, against `public_key` and keep it for
+    /// [`emit::Signed`]. Signing the canonical form rather than the file lets a crate that only
+    /// embeds the parsed config verify it again at runtime.
+    ///
+    /// A signature that does not match is always an error. A missing signature is an error in
+    /// release builds and a warning otherwise, so that development builds can use unsigned
+    /// configs. In-source configs are part of the crate and are never signed.
+    ///
+    /// The signature file is tracked for rebuilds. A missing one is only tracked in release
+    /// builds, as cargo reruns the build script on every build for missing files.
+    pub fn verify_signature(mut self, public_key: &[u8; 32]) -> Result<Self, Diagnostic> {
+        let Some(located) = &self.located else {
+            return Ok(self);
+        };
+        let release = is_release();
+
+        let mut sig_path = located.path.clone().into_os_string();
+        sig_path.push(".sig");
+        let sig_path = PathBuf::from(sig_path);
+        let describe = || format!("{} ({})", located.path.display(), located.source);
+
+        if release || sig_path.exists() {
+            instruction(&format!("cargo:rerun-if-changed={}", sig_path.display()));
+        }
+
+        let signature = match std::fs::read(&sig_path) {
+            Ok(signature) => signature,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let message = format!(
+                    "bootstrap config {} is not signed, {} does not exist",
+                    describe(),
+                    sig_path.display()
+                );
+                if release {
+                    return Err(Diagnostic::other(format!(
+                        "{message}; release builds refuse unsigned configs"
+                    )));
+                }
+                // not tracked, so a signature added later only takes effect once the config
+                // changes or the build script reruns for another reason.
+                warn!("{message}; release builds will refuse it");
+                return Ok(self);
+            }
+            Err(e) => {
+                return Err(Diagnostic::other(format!(
+                    "failed to read signature {}: {e}",
+                    sig_path.display()
+                )))
+            }
+        };
+
+        let verified = config_types::canonical_toml(&self.config)
+            .map_err(|e| e.to_string())
+            .and_then(|canonical| {
+                let key = VerifyingKey::from_bytes(public_key).map_err(|e| e.to_string())?;
+                let signature = Signature::from_slice(&signature).map_err(|e| e.to_string())?;
+                key.verify_strict(canonical.as_bytes(), &signature)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = verified {
+            let mut message = format!(
+                "signature {} does not match bootstrap config {}: {e}\nthe config changed since \
+                it was signed, or it was not signed with the config signing key",
+                sig_path.display(),
+                describe()
+            );
+            if self.migrated {
+                message.push_str(
+                    "\nthe config was migrated to the current layout when it was loaded, and \
+                    signatures cover the migrated config; sign it again",
+                );
+            }
+            return Err(Diagnostic::other(message));
+        }
+
+        self.signature = Some(signature);
+        Ok(self)
+    }
+}
+
+impl<T> Loaded<T> {
+    pub fn config(&self) -> &T {
+        &self.config
+    }
+
+    /// The config file exactly as it was read.
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// Where the config was read from, `None` for in-source configs.
+    pub fn located(&self) -> Option<&Located> {
+        self.located.as_ref()
+    }
+
+    /// Whether the config was written for an older layout and migrated when it was loaded, see
+    /// [`Located::load_migrated`].
+    pub fn migrated(&self) -> bool {
+        self.migrated
+    }
+
+    /// The verified signature of the config, see [`Loaded::verify_signature`]. `None` if it has
+    /// not been verified or the config is not signed.
+    pub fn signature(&self) -> Option<&[u8]> {
+        self.signature.as_deref()
+    }
+
+    /// Run a check that parsing alone does not cover, e.g. that the config has every key the
+    /// crate needs, failing the build with the message `check` returns.
+    pub fn validate(
+        self,
+        check: impl FnOnce(&T) -> Result<(), String>,
+    ) -> Result<Self, Diagnostic> {
+        match check(&self.config) {
+            Ok(()) => Ok(self),
+            Err(e) => Err(Diagnostic::other(format!(
+                "invalid {}\n{e}",
+                self.describe()
+            ))),
+        }
+    }
+
+    /// Report the fields of the config file that `T` has no place for, as listed by `find`.
+    /// Parsing ignores them, which hides typos, so depending on `policy` they fail the build or
+    /// each of them is a cargo warning.
+    pub fn check_unknown_fields(
+        self,
+        policy: UnknownFields,
+        find: impl FnOnce(&str) -> Result<Vec<String>, String>,
+    ) -> Result<Self, Diagnostic> {
+        let unknown = find(&self.contents)
+            .map_err(|e| Diagnostic::other(format!("invalid {}\n{e}", self.describe())))?;
+        if unknown.is_empty() {
+            return Ok(self);
+        }
+
+        if policy == UnknownFields::Deny {
+            return Err(Diagnostic::other(format!(
+                "invalid {}\n{}",
+                self.describe(),
+                unknown.join("\n")
+            )));
+        }
+        for field in unknown {
+            warn!("{}: {field}", self.describe());
+        }
+        warn!("unknown fields are ignored, fix or remove them before strict mode rejects them");
+        Ok(self)
+    }
+
+    fn describe(&self) -> String {
+        match &self.located {
+            Some(located) => format!(
+                "bootstrap config {} ({})",
+                located.path.display(),
+                located.source
+            ),
+            None => "in-source bootstrap config".to_string(),
+        }
+    }
+
+    /// Write out everything the crate needs to include to embed this config.
+    pub fn emit(&self, emitter: &impl Emitter<T>) -> Result<(), Diagnostic> {
+        emitter
+            .emit(self)
+            .map_err(|e| Diagnostic::other(format!("failed to emit bootstrap config: {e}")))
+    }
+}
+
+/// Parse `contents` as `T`, reporting the (1-based) line and column of any error.
+fn parse<T: DeserializeOwned>(contents: &str) -> Result<T, (usize, usize, toml::de::Error)> {
+    toml::from_str(contents).map_err(|e| {
+        let offset = e.span().map(|span| span.start).unwrap_or_default();
+        let (line, column) = line_column(contents, offset);
+        (line, column, e)
+    })
+}
+
+/// Convert a byte offset into the (1-based) line and column it falls on.
+fn line_column(s: &str, offset: usize) -> (usize, usize) {
+    let before = &s[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+/// What to do about fields of the config file the config type has no place for, see
+/// [`Loaded::check_unknown_fields`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownFields {
+    /// Report each of them as a cargo warning.
+    Warn,
+    /// Fail the build.
+    Deny,
+}
+
+impl UnknownFields {
+    /// The policy picked by the features of the crate being built: [`UnknownFields::Deny`] with
+    /// `strict-cfg`, or with `require-cfg` unless `lenient-cfg` is also enabled, as a config that
+    /// is required is also expected to be exact. [`UnknownFields::Warn`] otherwise.
+    pub fn from_features() -> Self {
+        let feature = |name: &str| std::env::var_os(format!("CARGO_FEATURE_{name}")).is_some();
+        if feature("STRICT_CFG") || (feature("REQUIRE_CFG") && !feature("LENIENT_CFG")) {
+            Self::Deny
+        } else {
+            Self::Warn
+        }
+    }
+}
+
+/// A problem with the bootstrap config, described for the person running the build.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    not_found: bool,
+    message: String,
+}
+
+impl Diagnostic {
+    fn not_found(message: String) -> Self {
+        Self {
+            not_found: true,
+            message,
+        }
+    }
+
+    fn other(message: String) -> Self {
+        Self {
+            not_found: false,
+            message,
+        }
+    }
+
+    /// The config file does not exist, which crates may choose to tolerate.
+    pub fn is_not_found(&self) -> bool {
+        self.not_found
+    }
+
+    /// Surface the problem as cargo warnings and fail the build through a `compile_error!`
+    /// rather than a build script panic and backtrace.
+    ///
+    /// The crate has to include the error when [`ERROR_CFG`] is set:
+    ///
+    /// ```ignore
+    /// #[cfg(bootstrap_config_error)]
+    /// include!(concat!(env!("OUT_DIR"), "/diagnostics.rs"));
+    /// ```
+    pub fn report(&self) {
+        for line in self.message.lines() {
+            warn!("{line}");
+        }
+
+        let error = format!("compile_error!({:?});\n", self.message.trim_end());
+        std::fs::write(out_dir().join(DIAGNOSTICS_FILE), error).unwrap();
+        instruction(&format!("cargo:rustc-cfg={ERROR_CFG}"));
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Example {
+        ip: String,
+    }
+
+    #[test]
+    fn naming() {
+        let bootstrap = Bootstrap::new("NYMVPN");
+        assert_eq!(bootstrap.env_var(), "NYMVPN_CONFIG_PATH");
+        assert_eq!(bootstrap.default_file, "nymvpn-config.toml");
+        assert_eq!(bootstrap.pubkey_env_var(), "NYMVPN_CONFIG_PUBKEY");
+    }
+
+    #[test]
+    fn captured_instructions() {
+        let bootstrap = Bootstrap::from_env("BOOTSTRAP_CAPTURE_TEST_PATH", "missing.toml");
+        let dev_key = [1; 32];
+
+        let (key, instructions) = capture(false, || bootstrap.verifying_key(&dev_key));
+        assert_eq!(key.unwrap(), dev_key);
+        assert!(instructions
+            .contains(&"cargo:rerun-if-env-changed=BOOTSTRAP_CAPTURE_TEST_PUBKEY".to_string()));
+
+        let (key, _) = capture(true, || bootstrap.verifying_key(&dev_key));
+        let err = key.unwrap_err().to_string();
+        assert!(err.contains("release builds refuse"), "{err}");
+    }
+
+    #[test]
+    fn parse_error_location() {
+        assert!(Loaded::<Example>::in_source("ip = \"127.0.0.1\"").is_ok());
+
+        let err = Loaded::<Example>::in_source("\nip = 127.0.0.1").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid in-source bootstrap config at 2:"));
+        assert!(!err.is_not_found());
+    }
+
+    #[test]
+    fn validation() {
+        let config = Loaded::<Example>::in_source("ip = \"127.0.0.1\"").unwrap();
+        let config = config
+            .validate(|config| match config.ip.as_str() {
+                "127.0.0.1" => Ok(()),
+                ip => Err(format!("unexpected ip {ip}")),
+            })
+            .unwrap();
+
+        let err = config
+            .validate(|_| Err("missing key".to_string()))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid in-source bootstrap config\nmissing key"
+        );
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let config = Loaded::<Example>::in_source("ip = \"127.0.0.1\"\nprt = 1").unwrap();
+        let find = |contents: &str| {
+            let table: toml::Table = toml::from_str(contents).map_err(|e| e.to_string())?;
+            Ok(table
+                .keys()
+                .filter(|key| *key != "ip")
+                .map(|key| format!("unknown field `{key}`"))
+                .collect())
+        };
+
+        let config = config
+            .check_unknown_fields(UnknownFields::Warn, find)
+            .unwrap();
+        let err = config
+            .check_unknown_fields(UnknownFields::Deny, find)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid in-source bootstrap config\nunknown field `prt`"
+        );
+    }
+
+    #[test]
+    fn workspace_root_discovery() {
+        let dir = std::env::temp_dir().join(format!("bootstrap-build-{}", std::process::id()));
+        let member = dir.join("crates/member");
+        let standalone = dir.join("standalone");
+        let explicit = dir.join("elsewhere/explicit");
+        for d in [&member, &standalone, &explicit] {
+            std::fs::create_dir_all(d).unwrap();
+        }
+
+        std::fs::write(dir.join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+        std::fs::write(member.join("Cargo.toml"), "[package]\nname = \"member\"\n").unwrap();
+        std::fs::write(
+            standalone.join("Cargo.toml"),
+            "[package]\nname = \"standalone\"\n[workspace]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            explicit.join("Cargo.toml"),
+            "[package]\nname = \"explicit\"\nworkspace = \"../../crates\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(find_workspace_root(&member), dir);
+        assert_eq!(find_workspace_root(&standalone), standalone);
+        assert_eq!(
+            find_workspace_root(&explicit),
+            explicit.join("../../crates")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn search_order() {
+        let dir = std::env::temp_dir().join(format!("bootstrap-search-{}", std::process::id()));
+        let (first, second) = (dir.join("first"), dir.join("second"));
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+
+        let bootstrap = Bootstrap::from_env("BOOTSTRAP_BUILD_TEST_UNSET", "search-test.toml")
+            .search_dir(SearchDir::Path(first.to_str().unwrap().to_string()))
+            .search_dir(SearchDir::Path("${BOOTSTRAP_BUILD_TEST_DIR}".to_string()));
+
+        // the second directory is only expanded once the first one came up empty
+        std::fs::write(first.join("search-test.toml"), "").unwrap();
+        let (located, deps) = bootstrap.resolve_tracked();
+        assert_eq!(located.unwrap().path(), first.join("search-test.toml"));
+        assert_eq!(
+            deps.files().last().unwrap(),
+            &first.join("search-test.toml")
+        );
+        assert!(!deps
+            .env_vars()
+            .contains(&"BOOTSTRAP_BUILD_TEST_DIR".to_string()));
+
+        std::fs::remove_file(first.join("search-test.toml")).unwrap();
+        let err = bootstrap.resolve().unwrap_err();
+        assert!(!err.is_not_found());
+        assert!(err
+            .to_string()
+            .contains("BOOTSTRAP_BUILD_TEST_DIR is not set"));
+
+        let bootstrap = Bootstrap::from_env("BOOTSTRAP_BUILD_TEST_UNSET", "search-test.toml")
+            .search_dir(SearchDir::Path(first.to_str().unwrap().to_string()))
+            .search_dir(SearchDir::Path(second.to_str().unwrap().to_string()));
+        let err = bootstrap.resolve().unwrap_err();
+        assert!(err.is_not_found());
+        assert!(err
+            .to_string()
+            .contains(&second.join("search-test.toml").display().to_string()));
+
+        std::fs::write(second.join("search-test.toml"), "").unwrap();
+        let located = bootstrap.resolve().unwrap();
+        assert_eq!(located.path(), second.join("search-test.toml"));
+        assert_eq!(
+            located.source(),
+            &Source::Default("BOOTSTRAP_BUILD_TEST_UNSET".to_string())
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn line_columns() {
+        let s = "ab\ncd\n\nef";
+        assert_eq!(line_column(s, 0), (1, 1));
+        assert_eq!(line_column(s, 1), (1, 2));
+        assert_eq!(line_column(s, 3), (2, 1));
+        assert_eq!(line_column(s, 7), (4, 1));
+    }
+}
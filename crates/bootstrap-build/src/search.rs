@@ -0,0 +1,150 @@
+//! Where config files are looked for when no environment variable names one.
+
+use crate::workspace_root;
+
+use std::path::PathBuf;
+
+/// A directory searched for the default config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchDir {
+    /// The root of the workspace, see [`workspace_root`].
+    WorkspaceRoot,
+    /// The directory of the crate being built (`CARGO_MANIFEST_DIR`).
+    CrateDir,
+    /// The given subdirectory of `$XDG_CONFIG_HOME`, or of `~/.config` if that is not set.
+    XdgConfig(String),
+    /// Any other directory. `~` and `${VAR}` are expanded, see [`expand`], and relative paths
+    /// are taken relative to the workspace root.
+    Path(String),
+}
+
+impl SearchDir {
+    /// The directory this stands for, or `None` if it cannot be determined in this environment.
+    pub(crate) fn resolve(&self, deps: &mut Dependencies) -> Result<Option<PathBuf>, String> {
+        let dir = match self {
+            SearchDir::WorkspaceRoot => workspace_root().ok(),
+            SearchDir::CrateDir => std::env::var("CARGO_MANIFEST_DIR").ok().map(PathBuf::from),
+            SearchDir::XdgConfig(subdir) => {
+                let config_home = deps
+                    .env_var("XDG_CONFIG_HOME")
+                    .filter(|dir| !dir.is_empty())
+                    .map(PathBuf::from)
+                    .or_else(|| deps.env_var("HOME").map(|home| home_config(&home)));
+                config_home.map(|dir| dir.join(subdir))
+            }
+            SearchDir::Path(path) => Some(relative_to_workspace(expand(path, deps)?)?),
+        };
+        Ok(dir)
+    }
+}
+
+fn home_config(home: &str) -> PathBuf {
+    PathBuf::from(home).join(".config")
+}
+
+/// Take `path` relative to the workspace root unless it is absolute.
+pub(crate) fn relative_to_workspace(path: String) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+    if path.is_absolute() {
+        return Ok(path);
+    }
+    Ok(workspace_root()?.join(path))
+}
+
+/// Everything outside of the crate that went into finding the config file, to be tracked so that
+/// a change to any of it triggers a rebuild.
+#[derive(Debug, Clone, Default)]
+pub struct Dependencies {
+    env_vars: Vec<String>,
+    files: Vec<PathBuf>,
+}
+
+impl Dependencies {
+    /// Environment variables that were read, whether or not they were set.
+    pub fn env_vars(&self) -> &[String] {
+        &self.env_vars
+    }
+
+    /// Candidate config files that were checked, whether or not they exist, up to and including
+    /// the one that was picked.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub(crate) fn env_var(&mut self, name: &str) -> Option<String> {
+        if !self.env_vars.iter().any(|var| var == name) {
+            self.env_vars.push(name.to_string());
+        }
+        std::env::var(name).ok()
+    }
+
+    pub(crate) fn file(&mut self, path: PathBuf) {
+        self.files.push(path);
+    }
+}
+
+/// Expand a leading `~` to the home directory and every `${VAR}` to the value of the environment
+/// variable `VAR`. Unset variables are an error rather than silently expanding to nothing, which
+/// could point the build at an unintended file.
+pub fn expand(path: &str, deps: &mut Dependencies) -> Result<String, String> {
+    let mut out = String::with_capacity(path.len());
+
+    let mut rest = path;
+    if rest == "~" || rest.starts_with("~/") {
+        let home = deps
+            .env_var("HOME")
+            .ok_or_else(|| format!("cannot expand `~` in `{path}`: HOME is not set"))?;
+        out.push_str(&home);
+        rest = &rest[1..];
+    }
+
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let var_end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unterminated `${{` in `{path}`"))?;
+        let name = &rest[start + 2..start + var_end];
+        let value = deps
+            .env_var(name)
+            .ok_or_else(|| format!("cannot expand `${{{name}}}` in `{path}`: {name} is not set"))?;
+        out.push_str(&value);
+        rest = &rest[start + var_end + 1..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expansion() {
+        let mut deps = Dependencies::default();
+        let manifest_dir = env!("CARGO_MANIFEST_DIR");
+
+        assert_eq!(expand("a/b", &mut deps).unwrap(), "a/b");
+        assert_eq!(
+            expand("${CARGO_MANIFEST_DIR}/x.toml", &mut deps).unwrap(),
+            format!("{manifest_dir}/x.toml")
+        );
+        assert_eq!(
+            expand("a${CARGO_PKG_NAME}b${CARGO_PKG_NAME}", &mut deps).unwrap(),
+            "abootstrap-buildbbootstrap-build"
+        );
+        assert_eq!(expand("$HOME/~", &mut deps).unwrap(), "$HOME/~");
+        if let Ok(home) = std::env::var("HOME") {
+            assert_eq!(expand("~/x", &mut deps).unwrap(), format!("{home}/x"));
+        }
+
+        assert_eq!(
+            deps.env_vars()[..2],
+            ["CARGO_MANIFEST_DIR", "CARGO_PKG_NAME"]
+        );
+
+        let err = expand("${BOOTSTRAP_BUILD_TEST_UNSET}", &mut deps).unwrap_err();
+        assert!(err.contains("BOOTSTRAP_BUILD_TEST_UNSET is not set"));
+        assert!(expand("${UNTERMINATED", &mut deps).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! The `#[bootstrap]` attribute, re-exported and documented by the `bootstrap` crate.
+
+use bootstrap_build::{emit::config_expr, Bootstrap, UnknownFields};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Expr, ExprArray, Fields, ItemStruct, Lit, LitStr};
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+#[derive(Default)]
+struct Args {
+    env: Option<LitStr>,
+    default_file: Option<LitStr>,
+    feature: Option<LitStr>,
+    fallback: Option<Expr>,
+    required_keys: Vec<LitStr>,
+}
+
+#[proc_macro_attribute]
+pub fn bootstrap(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = Args::default();
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("env") {
+            args.env = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("default_file") {
+            args.default_file = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("feature") {
+            args.feature = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("fallback") {
+            args.fallback = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("required_keys") {
+            let keys: ExprArray = meta.value()?.parse()?;
+            for key in keys.elems {
+                match key {
+                    Expr::Lit(syn::ExprLit {
+                        lit: Lit::Str(key), ..
+                    }) => args.required_keys.push(key),
+                    key => return Err(syn::Error::new_spanned(key, "expected a string literal")),
+                }
+            }
+        } else {
+            return Err(meta.error(
+                "expected `env`, `default_file`, `feature`, `fallback`, or `required_keys`",
+            ));
+        }
+        Ok(())
+    });
+    parse_macro_input!(attr with parser);
+    let item = parse_macro_input!(item as ItemStruct);
+
+    expand(args, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(args: Args, item: ItemStruct) -> syn::Result<TokenStream2> {
+    let span = proc_macro2::Span::call_site();
+    let env = args
+        .env
+        .ok_or_else(|| syn::Error::new(span, "missing `env = \"...\"`"))?;
+    let default_file = args
+        .default_file
+        .ok_or_else(|| syn::Error::new(span, "missing `default_file = \"...\"`"))?;
+
+    let (cfg_enabled, fallback_impl) = match (&args.feature, &args.fallback) {
+        (Some(feature), Some(fallback)) => {
+            let parsed = quote! {
+                ::bootstrap::__private::toml::from_str(#fallback).expect("invalid fallback config")
+            };
+            let fallback = wrap(&item, parsed);
+            let name = &item.ident;
+            let fallback_impl = quote! {
+                #[cfg(not(feature = #feature))]
+                impl ::std::default::Default for #name {
+                    fn default() -> Self {
+                        #fallback
+                    }
+                }
+            };
+            (quote! { #[cfg(feature = #feature)] }, fallback_impl)
+        }
+        (None, None) => (TokenStream2::new(), TokenStream2::new()),
+        (Some(feature), None) => {
+            return Err(syn::Error::new(
+                feature.span(),
+                "`feature` requires a `fallback` config for when the feature is disabled",
+            ))
+        }
+        (None, Some(_)) => {
+            return Err(syn::Error::new(
+                span,
+                "`fallback` is only used together with `feature`",
+            ))
+        }
+    };
+
+    let bootstrap = Bootstrap::from_env(&env.value(), &default_file.value());
+    let name = &item.ident;
+
+    let required_keys: Vec<String> = args.required_keys.iter().map(LitStr::value).collect();
+    let required_keys: Vec<&str> = required_keys.iter().map(String::as_str).collect();
+
+    // the macro is not told the profile, so the config is checked the way both a debug and a
+    // release build script would, and what only release builds refuse is an error without
+    // `debug_assertions`. Warnings are dropped, a proc-macro has no way of reporting them.
+    let check = |release| {
+        bootstrap_build::capture(release, || {
+            bootstrap.load_checked(&required_keys, UnknownFields::Warn)
+        })
+    };
+    let (loaded, instructions) = check(false);
+    let (release, release_instructions) = check(true);
+
+    // the macro reads the environment and the files itself, which cargo does not know about.
+    // Referencing them through the compiler records them in the dep-info so that changing any of
+    // them triggers a rebuild. Unused consts are not part of the binary. Candidate files that do
+    // not exist cannot be referenced, so creating one of them is not picked up.
+    let mut env_vars = BTreeSet::new();
+    let mut files = BTreeSet::new();
+    for instruction in instructions.iter().chain(&release_instructions) {
+        if let Some(env_var) = instruction.strip_prefix("cargo:rerun-if-env-changed=") {
+            env_vars.insert(env_var);
+        } else if let Some(file) = instruction.strip_prefix("cargo:rerun-if-changed=") {
+            files.insert(file);
+        }
+    }
+    let mut tracking = TokenStream2::new();
+    for env_var in env_vars {
+        tracking.extend(quote! { const _: Option<&str> = option_env!(#env_var); });
+    }
+    for file in files.into_iter().filter(|file| Path::new(file).is_file()) {
+        tracking.extend(quote! { const _: &[u8] = include_bytes!(#file); });
+    }
+
+    let embedded = match loaded {
+        Ok(loaded) => {
+            let value =
+                toml::Value::try_from(loaded.config()).map_err(|e| syn::Error::new(span, e))?;
+            let expr: TokenStream2 = config_expr(&value).parse()?;
+            let config = wrap(&item, expr);
+            let release_error = release.err().map(|diagnostic| {
+                let message = diagnostic.to_string();
+                quote! {
+                    #cfg_enabled
+                    #[cfg(not(debug_assertions))]
+                    compile_error!(#message);
+                }
+            });
+            quote! {
+                #cfg_enabled
+                impl ::std::default::Default for #name {
+                    fn default() -> Self {
+                        use ::bootstrap::__private::{toml, unmask};
+
+                        #config
+                    }
+                }
+
+                #release_error
+            }
+        }
+        Err(diagnostic) => {
+            let message = diagnostic.to_string();
+            quote! {
+                #cfg_enabled
+                compile_error!(#message);
+
+                #cfg_enabled
+                impl ::std::default::Default for #name {
+                    fn default() -> Self {
+                        unreachable!()
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(quote! {
+        #item
+
+        const _: () = {
+            #tracking
+        };
+
+        #embedded
+
+        #fallback_impl
+    })
+}
+
+/// Turn an expression producing the config into one producing the annotated struct, which is
+/// either a newtype around `BaseConfig` or deserialized from it. Naming the type makes a newtype
+/// around anything else a type error at compile time.
+fn wrap(item: &ItemStruct, config: TokenStream2) -> TokenStream2 {
+    match &item.fields {
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote! {
+            Self({
+                let config: ::bootstrap::__private::BaseConfig = #config;
+                config
+            })
+        },
+        _ => config,
+    }
+}
@@ -0,0 +1,51 @@
+//! Embed a config file into a crate at compile time without a build script.
+//!
+//! ```ignore
+//! use bootstrap::bootstrap;
+//! use config_types::BaseConfig;
+//!
+//! #[bootstrap(
+//!     env = "NYMVPN_CONFIG_PATH",
+//!     default_file = "nymvpn-config.toml",
+//!     feature = "enable-cfg",
+//!     fallback = config_types::DEFAULT_CONFIG_TOML_STR,
+//!     required_keys = ["github"],
+//! )]
+//! pub struct Config(BaseConfig);
+//! ```
+//!
+//! See [`bootstrap`] for the arguments.
+
+/// Implement `Default` for a struct by embedding the config file found at compile time.
+///
+/// The config file is named by the environment variable `env`, or is `default_file` if it is not
+/// set. Relative paths are taken relative to the workspace root (`CARGO_WORKSPACE_DIR`). Every
+/// string in the config is masked the same way as by the `Masked` emitter of `bootstrap-build`.
+///
+/// The struct is either a newtype around `config_types::BaseConfig`, or has to implement
+/// `serde::Deserialize` itself and is deserialized from the `BaseConfig` at runtime. The generated
+/// code refers to this crate as `::bootstrap`, so it must not be renamed in `Cargo.toml`.
+///
+/// - `env`: environment variable naming the config file (required).
+/// - `default_file`: the config file used when `env` is not set (required).
+/// - `feature`: only embed the config when this feature of the crate is enabled. Problems with the
+///   config file are only reported with the feature enabled.
+/// - `fallback`: expression of type `&str`, the toml config used when `feature` is not enabled.
+///   Required if and only if `feature` is given.
+/// - `required_keys`: array of the names of keys the config has to provide (optional).
+///
+/// The config is checked at compile time the same way the build scripts of bootstraps1 and
+/// bootstraps2 do: old layouts are migrated, unknown fields and missing required keys are looked
+/// for, and the signature is verified against the key in `env` with `_PATH` replaced by `_PUBKEY`,
+/// e.g. `NYMVPN_CONFIG_PUBKEY`. Any problem fails the build with a `compile_error!`. Release
+/// builds also refuse unsigned configs and the development key; as the macro is not told the
+/// profile, builds without `debug_assertions` are taken to be release builds. Warnings, e.g. for
+/// migrations or unknown fields, cannot be reported from a macro and are dropped.
+pub use bootstrap_macros::bootstrap;
+
+/// Used by the code generated by [`bootstrap`], not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use config_types::{unmask, BaseConfig};
+    pub use toml;
+}
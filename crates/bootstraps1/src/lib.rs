@@ -1,43 +1,158 @@
-use config_types::BaseConfig;
+use config_types::{BaseConfig, BuildInfo};
 use std::str::FromStr;
 
-#[cfg(feature = "enable-cfg")]
+/// Keys the build script requires every embedded config to provide.
+pub use config_types::REQUIRED_KEYS;
+
+// problems with the config file are reported by the build script as a `compile_error!`
+#[cfg(bootstrap_config_error)]
+include!(concat!(env!("OUT_DIR"), "/diagnostics.rs"));
+
+#[cfg(all(feature = "enable-cfg", not(feature = "strip-paths")))]
 include!(concat!(env!("OUT_DIR"), "/obfuscated.rs"));
 
+#[cfg(all(feature = "enable-cfg", feature = "strip-paths"))]
+static BOOTSTRAP_CONFIG_SEALED: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/config.sealed"));
+#[cfg(all(feature = "enable-cfg", feature = "strip-paths"))]
+static BOOTSTRAP_CONFIG_NONCE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/config.nonce"));
+#[cfg(all(feature = "enable-cfg", feature = "strip-paths"))]
+static BOOTSTRAP_KEY_SHARE_A: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/key.share_a"));
+#[cfg(all(feature = "enable-cfg", feature = "strip-paths"))]
+static BOOTSTRAP_KEY_SHARE_B: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/key.share_b"));
+
+// set by the build script when the embedded config came with a valid signature, which is checked
+// again every time the config is read so that a patched binary does not hand out a patched config.
+#[cfg(bootstrap_config_signed)]
+static BOOTSTRAP_CONFIG_SIGNATURE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/config.sig"));
+
+// reserved for a config written into the binary after the build, see `config_types::patch`.
+// Mach-O section names take a segment and are limited to 16 characters, so Apple targets use
+// `__DATA,__nymcfg`. `nymcfg patch` only handles ELF binaries.
+#[cfg(feature = "patchable-cfg")]
+#[used]
+#[cfg_attr(not(target_vendor = "apple"), link_section = ".nymcfg")]
+#[cfg_attr(target_vendor = "apple", link_section = "__DATA,__nymcfg")]
+static PATCHED_CONFIG: [u8; config_types::patch::SECTION_SIZE] = config_types::patch::placeholder();
+
 pub struct Config(BaseConfig);
 
 /// Default Configuration used if no bootstrap configuration file is provided at compile time.
+#[cfg(not(feature = "require-cfg"))]
 const DEFAULT_CONFIG_STR: &str = r#"
-ip = "192.168.1.1"
-port = 4433
+version = 1
 
 [keys]
 github = "00000000000000000"
 travis = "11111111111111111"
+
+[[endpoint]]
+host = "192.168.1.1"
+port = 4433
 "#;
 
+/// Which config this build embedded, as recorded in the provenance manifest written by the build
+/// script, without exposing the config itself.
+pub fn build_info() -> BuildInfo {
+    BuildInfo::new(
+        env!("CARGO_PKG_VERSION"),
+        env!("BOOTSTRAP_FEATURES"),
+        env!("BOOTSTRAP_CONFIG_SHA256"),
+    )
+}
+
+/// The key the build script checked the config against, from the build environment, which the
+/// config is checked against again at runtime.
+#[cfg(any(bootstrap_config_signed, feature = "patchable-cfg"))]
+fn verifying_key() -> [u8; 32] {
+    config_types::verifying_key_from_hex(env!("BOOTSTRAP_CONFIG_PUBKEY"))
+        .expect("invalid config signing key")
+}
+
 impl AsRef<BaseConfig> for Config {
     fn as_ref(&self) -> &BaseConfig {
         &self.0
     }
 }
 
+/// Open the sealed configuration embedded at compile time.
+///
+/// Returns `None` if bootstrapping was enabled but no config file was provided at build time.
+#[cfg(feature = "enable-cfg")]
+fn open_bootstrap_config() -> Option<String> {
+    use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+
+    if BOOTSTRAP_CONFIG_SEALED.is_empty() {
+        return None;
+    }
+
+    // black_box keeps the compiler from recombining the key shares at compile time.
+    let share_a = std::hint::black_box(BOOTSTRAP_KEY_SHARE_A);
+    let share_b = std::hint::black_box(BOOTSTRAP_KEY_SHARE_B);
+    let key: Vec<u8> = share_a.iter().zip(share_b).map(|(a, b)| a ^ b).collect();
+
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(
+            Nonce::from_slice(BOOTSTRAP_CONFIG_NONCE),
+            BOOTSTRAP_CONFIG_SEALED,
+        )
+        .expect("failed to open bootstrap config");
+
+    Some(String::from_utf8(plaintext).expect("bootstrap config is not valid utf-8"))
+}
+
+/// Open the config written into the `.nymcfg` section after the build, verifying its signature.
+///
+/// Returns `None` if the section was never patched.
+#[cfg(feature = "patchable-cfg")]
+fn open_patched_config() -> Option<BaseConfig> {
+    use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+    use config_types::patch::SealedConfig;
+
+    // black_box keeps the compiler from reading the placeholder it was compiled with instead of
+    // the patched section.
+    let section = std::hint::black_box(&PATCHED_CONFIG);
+    let sealed =
+        SealedConfig::from_section(section).expect("patched bootstrap config is invalid")?;
+
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(&sealed.key()))
+        .decrypt(
+            Nonce::from_slice(&sealed.nonce),
+            sealed.ciphertext.as_slice(),
+        )
+        .expect("failed to open patched bootstrap config");
+    let config_str = String::from_utf8(plaintext).expect("bootstrap config is not valid utf-8");
+
+    let config = BaseConfig::from_str(&config_str).expect("failed to parse patched config");
+    config
+        .verify_signature(&verifying_key(), &sealed.signature)
+        .expect("patched bootstrap config does not match its signature");
+    Some(config)
+}
+
 impl Default for Config {
+    /// The config patched into the binary if there is one, otherwise the one embedded at build
+    /// time, or the in-source default.
     fn default() -> Self {
+        #[cfg(feature = "patchable-cfg")]
+        if let Some(config) = open_patched_config() {
+            return Self(config);
+        }
+
         #[cfg(feature = "require-cfg")]
-        let config_str = BOOTSTRAP_CONFIG_STR;
+        let config_str = open_bootstrap_config().expect("no bootstrap config was embedded");
 
         #[cfg(all(not(feature = "require-cfg"), feature = "enable-cfg"))]
-        let config_str = if !BOOTSTRAP_CONFIG_STR.is_empty() {
-            BOOTSTRAP_CONFIG_STR
-        } else {
-            DEFAULT_CONFIG_STR
-        };
+        let config_str = open_bootstrap_config().unwrap_or_else(|| DEFAULT_CONFIG_STR.to_string());
 
         #[cfg(not(feature = "enable-cfg"))]
-        let config_str = DEFAULT_CONFIG_STR;
+        let config_str = DEFAULT_CONFIG_STR.to_string();
+
+        let config = BaseConfig::from_str(&config_str).expect("failed to parse default config");
 
-        let config = BaseConfig::from_str(config_str).expect("failed to parse default config");
+        #[cfg(bootstrap_config_signed)]
+        config
+            .verify_signature(&verifying_key(), BOOTSTRAP_CONFIG_SIGNATURE)
+            .expect("embedded bootstrap config does not match its signature");
 
         Self(config)
     }
@@ -51,23 +166,67 @@ mod tests {
     fn it_works() {
         #[cfg(feature = "enable-cfg")]
         {
-            println!("{:?}", BOOTSTRAP_CONFIG_STR);
             let c = Config::default();
             let config = c.as_ref();
-            assert_eq!(config.ip, "127.0.0.1");
-            assert_eq!(config.port, None);
-            assert_eq!(config.keys.github, "xxxxxxxxxxxxxxxxx");
-            assert_eq!(config.keys.travis.as_ref().unwrap(), "yyyyyyyyyyyyyyyyy");
+            assert_eq!(config.endpoints[0].host.to_string(), "127.0.0.1");
+            assert_eq!(config.endpoints[0].port, None);
+            assert_eq!(
+                config.keys.get("github").unwrap().expose(),
+                "xxxxxxxxxxxxxxxxx"
+            );
+            assert_eq!(
+                config.keys.get("travis").unwrap().expose(),
+                "yyyyyyyyyyyyyyyyy"
+            );
         }
 
         #[cfg(not(feature = "enable-cfg"))]
         {
             let c = Config::default();
             let config = c.0;
-            assert_eq!(config.ip, "192.168.1.1");
-            assert_eq!(config.port, Some(4433));
-            assert_eq!(config.keys.github, "00000000000000000");
-            assert_eq!(config.keys.travis.as_ref().unwrap(), "11111111111111111");
+            assert_eq!(config.endpoints[0].host.to_string(), "192.168.1.1");
+            assert_eq!(config.endpoints[0].port, Some(4433));
+            assert_eq!(
+                config.keys.get("github").unwrap().expose(),
+                "00000000000000000"
+            );
+            assert_eq!(
+                config.keys.get("travis").unwrap().expose(),
+                "11111111111111111"
+            );
         }
     }
+
+    #[test]
+    fn build_info_is_recorded() {
+        let info = build_info();
+        assert_eq!(info.version, "0.1.0");
+        assert_eq!(
+            info.features().any(|feature| feature == "enable-cfg"),
+            cfg!(feature = "enable-cfg")
+        );
+
+        #[cfg(feature = "enable-cfg")]
+        {
+            let sha256 = info.config_sha256.unwrap();
+            assert_eq!(sha256.len(), 64);
+            assert!(sha256.chars().all(|c| c.is_ascii_hexdigit()));
+        }
+
+        #[cfg(not(feature = "enable-cfg"))]
+        assert_eq!(info.config_sha256, None);
+    }
+
+    #[cfg(feature = "enable-cfg")]
+    #[test]
+    fn config_is_sealed() {
+        let config_str = open_bootstrap_config().unwrap();
+        assert!(config_str.contains("xxxxxxxxxxxxxxxxx"));
+
+        // neither the plaintext nor the key should be present in the generated values
+        assert!(!BOOTSTRAP_CONFIG_SEALED
+            .windows(config_str.len())
+            .any(|w| w == config_str.as_bytes()));
+        assert_ne!(BOOTSTRAP_KEY_SHARE_A, BOOTSTRAP_KEY_SHARE_B);
+    }
 }
@@ -1,86 +1,79 @@
-use config_types::BaseConfig;
-use std::{
-    io::{BufWriter, Write},
-    str::FromStr,
+use bootstrap_build::{
+    emit::{Masked, Provenance, Signed},
+    Loaded,
 };
+#[cfg(feature = "enable-cfg")]
+use bootstrap_build::{Bootstrap, UnknownFields};
+use config_types::BaseConfig;
 
-fn main() {
-    write_conditional_default();
-
-    println!("cargo:rerun-if-changed=build.rs");
-}
-
-const DEFAULT_CONFIG_PREAMBLE: &[u8] = br#"
+#[cfg(feature = "enable-cfg")]
+const DEFAULT_CONFIG_TEMPLATE: &str = r#"
 impl Default for Config {
     fn default() -> Self {
-        use config_types::Keys;
-
-        Self("#;
-
-const DEFAULT_CONFIG_EPILOGUE: &[u8] = br#"
-        )
+        Self(verified(
+            #[cfg(feature="enable-cfg")]
+            {config}
+        ))
     }
 }"#;
 
-#[cfg(feature = "enable-cfg")]
-const CUSTOM_BREAK: &[u8] = br#"
-            #[cfg(feature="enable-cfg")]
-            "#;
-
 #[cfg(not(feature = "enable-cfg"))]
-const DEFAULT_BREAK: &[u8] = br#"
+const DEFAULT_CONFIG_TEMPLATE: &str = r#"
+impl Default for Config {
+    fn default() -> Self {
+        Self(verified(
             #[cfg(not(feature="enable-cfg"))]
-            "#;
+            {config}
+        ))
+    }
+}"#;
 
-fn write_conditional_default() {
-    // creating a string with the values from our default Config object
-    let mut array_string = BufWriter::new(Vec::new());
-    array_string.write(DEFAULT_CONFIG_PREAMBLE).unwrap();
+fn main() {
+    bootstrap_build::init();
 
+    // on failure the in-source default is used so that the crate still compiles far enough for
+    // the diagnostic to be the only error reported. `embedded` is false whenever the in-source
+    // default is used, which is not recorded as an embedded config.
     #[cfg(feature = "enable-cfg")]
+    let (config, embedded) = match Bootstrap::new("NYMVPN")
+        .deny_tracked(cfg!(feature = "deny-tracked-cfg"))
+        .load_checked(config_types::REQUIRED_KEYS, UnknownFields::from_features())
     {
-        array_string.write(CUSTOM_BREAK).unwrap();
-        write_custom_config(&mut array_string);
-    }
+        Ok(config) => (config, true),
+        #[cfg(not(feature = "require-cfg"))]
+        Err(e) if e.is_not_found() => {
+            bootstrap_build::warn!(
+                "config bootstrapping was enabled, but no config file was found, falling back to \
+                the in-source default: {e}"
+            );
+            (default_config(), false)
+        }
+        Err(e) => {
+            e.report();
+            (default_config(), false)
+        }
+    };
 
     #[cfg(not(feature = "enable-cfg"))]
-    {
-        let default_config = BaseConfig::from_str(config_types::DEFAULT_CONFIG_TOML_STR).unwrap();
-        array_string.write(DEFAULT_BREAK).unwrap();
-        uneval::write(default_config, &mut array_string).unwrap();
+    let (config, embedded) = (default_config(), false);
+
+    let masked = Masked::new(DEFAULT_CONFIG_TEMPLATE).strip_paths(cfg!(feature = "strip-paths"));
+    let result = config
+        .emit(&masked)
+        .and_then(|()| config.emit(&Signed))
+        .and_then(|()| {
+            if embedded {
+                config.emit(&Provenance)
+            } else {
+                Provenance.emit_empty().unwrap();
+                Ok(())
+            }
+        });
+    if let Err(diagnostic) = result {
+        diagnostic.report();
     }
-
-    array_string.write(DEFAULT_CONFIG_EPILOGUE).unwrap();
-
-    // write the string to a file. OUT_DIR environment variable is defined by cargo
-    let out_dir = std::env::var("OUT_DIR").unwrap();
-    let dest_path = std::path::Path::new(&out_dir).join("default.rs");
-
-    let out_str = String::from_utf8(array_string.into_inner().unwrap()).unwrap();
-    std::fs::write(&dest_path, out_str).unwrap();
 }
 
-#[cfg(feature = "enable-cfg")]
-pub(crate) fn write_custom_config(out: impl Write) {
-    // allow the name of the file we draw hardcoded values from to be set by an
-    // environment variable at compile time.
-    let cfg_file_name = option_env!("NYMVPN_CONFIG_PATH").unwrap_or("nymvpn-config.toml");
-
-    // set reasons to rebuild
-    println!("cargo:rerun-if-changed={cfg_file_name}");
-    println!("cargo:rerun-if-env-changed=NYMVPN_CONFIG_PATH");
-
-    let path = std::path::PathBuf::from(cfg_file_name);
-    let cfg_file_path = if path.is_absolute() {
-        path
-    } else {
-        // CARGO_WORKSPACE_DIR is set in .cargo/config.toml - it is NOT provided by cargo itself
-        let workspace_path = std::env::var("CARGO_WORKSPACE_DIR").unwrap();
-        std::path::Path::new(&workspace_path).join(cfg_file_name)
-    };
-
-    let config_str: String = std::fs::read_to_string(cfg_file_path).unwrap();
-    let config = BaseConfig::from_str(&config_str).unwrap();
-
-    uneval::write(config, out).unwrap();
+fn default_config() -> Loaded<BaseConfig> {
+    Loaded::in_source(config_types::DEFAULT_CONFIG_TOML_STR).unwrap()
 }
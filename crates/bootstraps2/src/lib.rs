@@ -1,9 +1,52 @@
-use config_types::BaseConfig;
+use config_types::{unmask, BaseConfig, BuildInfo};
 
 pub struct Config(BaseConfig);
 
+// problems with the config file are reported by the build script as a `compile_error!`
+#[cfg(bootstrap_config_error)]
+include!(concat!(env!("OUT_DIR"), "/diagnostics.rs"));
+
+#[cfg(not(feature = "strip-paths"))]
 include!(concat!(env!("OUT_DIR"), "/default.rs"));
 
+#[cfg(feature = "strip-paths")]
+impl Default for Config {
+    fn default() -> Self {
+        use std::str::FromStr;
+
+        let masked = include_bytes!(concat!(env!("OUT_DIR"), "/config.masked"));
+        let mask = include_bytes!(concat!(env!("OUT_DIR"), "/config.mask"));
+
+        let config =
+            BaseConfig::from_str(&unmask(masked, mask)).expect("failed to parse embedded config");
+        Self(verified(config))
+    }
+}
+
+/// Check the embedded config against the signature the build script verified, if it was signed,
+/// so that a patched binary does not hand out a patched config.
+fn verified(config: BaseConfig) -> BaseConfig {
+    #[cfg(bootstrap_config_signed)]
+    config
+        .verify_signature(
+            &config_types::verifying_key_from_hex(env!("BOOTSTRAP_CONFIG_PUBKEY"))
+                .expect("invalid config signing key"),
+            include_bytes!(concat!(env!("OUT_DIR"), "/config.sig")),
+        )
+        .expect("embedded bootstrap config does not match its signature");
+    config
+}
+
+/// Which config this build embedded, as recorded in the provenance manifest written by the build
+/// script, without exposing the config itself.
+pub fn build_info() -> BuildInfo {
+    BuildInfo::new(
+        env!("CARGO_PKG_VERSION"),
+        env!("BOOTSTRAP_FEATURES"),
+        env!("BOOTSTRAP_CONFIG_SHA256"),
+    )
+}
+
 impl AsRef<BaseConfig> for Config {
     fn as_ref(&self) -> &BaseConfig {
         &self.0
@@ -20,20 +63,61 @@ mod tests {
         {
             let c = Config::default();
             let config = c.as_ref();
-            assert_eq!(config.ip, "127.0.0.1");
-            assert_eq!(config.port, None);
-            assert_eq!(config.keys.github, "xxxxxxxxxxxxxxxxx");
-            assert_eq!(config.keys.travis.as_ref().unwrap(), "yyyyyyyyyyyyyyyyy");
+            assert_eq!(config.endpoints[0].host.to_string(), "127.0.0.1");
+            assert_eq!(config.endpoints[0].port, None);
+            assert_eq!(
+                config.keys.get("github").unwrap().expose(),
+                "xxxxxxxxxxxxxxxxx"
+            );
+            assert_eq!(
+                config.keys.get("travis").unwrap().expose(),
+                "yyyyyyyyyyyyyyyyy"
+            );
         }
 
         #[cfg(not(feature = "enable-cfg"))]
         {
             let c = Config::default();
             let config = c.0;
-            assert_eq!(config.ip, "192.168.1.1");
-            assert_eq!(config.port, Some(4433));
-            assert_eq!(config.keys.github, "00000000000000000");
-            assert_eq!(config.keys.travis.as_ref().unwrap(), "11111111111111111");
+            assert_eq!(config.endpoints[0].host.to_string(), "192.168.1.1");
+            assert_eq!(config.endpoints[0].port, Some(4433));
+            assert_eq!(
+                config.keys.get("github").unwrap().expose(),
+                "00000000000000000"
+            );
+            assert_eq!(
+                config.keys.get("travis").unwrap().expose(),
+                "11111111111111111"
+            );
+        }
+    }
+
+    #[test]
+    fn build_info_is_recorded() {
+        let info = build_info();
+        assert_eq!(info.version, "0.1.0");
+        assert_eq!(
+            info.features().any(|feature| feature == "enable-cfg"),
+            cfg!(feature = "enable-cfg")
+        );
+
+        #[cfg(feature = "enable-cfg")]
+        {
+            let sha256 = info.config_sha256.unwrap();
+            assert_eq!(sha256.len(), 64);
+            assert!(sha256.chars().all(|c| c.is_ascii_hexdigit()));
         }
+
+        #[cfg(not(feature = "enable-cfg"))]
+        assert_eq!(info.config_sha256, None);
+    }
+
+    #[cfg(not(feature = "strip-paths"))]
+    #[test]
+    fn strings_are_masked() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/default.rs"));
+        assert!(!generated.contains(".into()"));
+        assert!(!generated.contains("xxxxxxxxxxxxxxxxx"));
+        assert!(generated.contains("unmask(&["));
     }
 }
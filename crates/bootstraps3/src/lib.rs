@@ -0,0 +1,69 @@
+use bootstrap::bootstrap;
+use config_types::BaseConfig;
+
+#[bootstrap(
+    env = "NYMVPN_CONFIG_PATH",
+    default_file = "nymvpn-config.toml",
+    feature = "enable-cfg",
+    fallback = config_types::DEFAULT_CONFIG_TOML_STR,
+    required_keys = ["github"]
+)]
+pub struct Config(BaseConfig);
+
+impl AsRef<BaseConfig> for Config {
+    fn as_ref(&self) -> &BaseConfig {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let c = Config::default();
+        let config = c.as_ref();
+
+        #[cfg(feature = "enable-cfg")]
+        {
+            assert_eq!(config.endpoints[0].host.to_string(), "127.0.0.1");
+            assert_eq!(config.endpoints[0].port, None);
+            assert_eq!(
+                config.keys.get("github").unwrap().expose(),
+                "xxxxxxxxxxxxxxxxx"
+            );
+            assert_eq!(
+                config.keys.get("travis").unwrap().expose(),
+                "yyyyyyyyyyyyyyyyy"
+            );
+        }
+
+        #[cfg(not(feature = "enable-cfg"))]
+        {
+            assert_eq!(config.endpoints[0].host.to_string(), "192.168.1.1");
+            assert_eq!(config.endpoints[0].port, Some(4433));
+            assert_eq!(
+                config.keys.get("github").unwrap().expose(),
+                "00000000000000000"
+            );
+            assert_eq!(
+                config.keys.get("travis").unwrap().expose(),
+                "11111111111111111"
+            );
+        }
+    }
+
+    /// Structs that are not a newtype are deserialized from the config directly.
+    #[bootstrap(env = "NYMVPN_CONFIG_PATH", default_file = "nymvpn-config.toml")]
+    #[derive(serde::Deserialize)]
+    struct Keys {
+        keys: config_types::Keys,
+    }
+
+    #[test]
+    fn deserializes_directly() {
+        let keys = Keys::default().keys;
+        assert_eq!(keys.get("github").unwrap().expose(), "xxxxxxxxxxxxxxxxx");
+    }
+}
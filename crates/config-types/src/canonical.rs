@@ -0,0 +1,37 @@
+//! The canonical form of a config, which config signatures and the provenance hash cover.
+//!
+//! Build scripts, the bootstraps crates at runtime, and `nymcfg` all go through these functions,
+//! so that a hash or signature made by one of them always matches the others.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// `config` serialized to toml with every table sorted by key, so that it only depends on the
+/// values in the config and not on how the file was written.
+pub fn canonical_toml<T: Serialize>(config: &T) -> Result<String, toml::ser::Error> {
+    toml::to_string(&toml::Value::try_from(config)?)
+}
+
+/// SHA-256 of the [`canonical_toml`] form of `config`, hex encoded.
+pub fn canonical_sha256<T: Serialize>(config: &T) -> Result<String, toml::ser::Error> {
+    let canonical = canonical_toml(config)?;
+    let hash = Sha256::digest(canonical.as_bytes());
+    Ok(hash.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_hash() {
+        let a: toml::Value = toml::from_str("b = 2\na = \"x\"  # comment\n").unwrap();
+        let b: toml::Value = toml::from_str("a = 'x'\nb = 2\n").unwrap();
+        let c: toml::Value = toml::from_str("a = 'y'\nb = 2\n").unwrap();
+
+        let hash = canonical_sha256(&a).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, canonical_sha256(&b).unwrap());
+        assert_ne!(hash, canonical_sha256(&c).unwrap());
+    }
+}
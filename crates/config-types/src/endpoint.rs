@@ -0,0 +1,174 @@
+//! Bootstrap endpoints and the order to try them in.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use crate::Host;
+
+/// Port used when an endpoint does not set one.
+pub const DEFAULT_PORT: u16 = 4433;
+
+/// A host a client can bootstrap from, an `[[endpoint]]` entry in the config.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Endpoint {
+    pub host: Host,
+    /// See [`Endpoint::port`].
+    pub port: Option<u16>,
+    /// Endpoints with a lower priority are tried first.
+    #[serde(default)]
+    pub priority: u32,
+    /// How often this endpoint is tried before the others with the same priority, relative to
+    /// their weights. Endpoints with a weight of 0 are only tried after all others with the same
+    /// priority.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Free-form tag, e.g. `eu-west`, for clients that prefer nearby endpoints.
+    pub region: Option<String>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl Endpoint {
+    /// An endpoint with the default priority and weight and no region.
+    pub fn new(host: Host, port: Option<u16>) -> Self {
+        Self {
+            host,
+            port,
+            priority: 0,
+            weight: default_weight(),
+            region: None,
+        }
+    }
+
+    /// The port to bootstrap from, [`DEFAULT_PORT`] if the endpoint does not set one.
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_PORT)
+    }
+
+    /// The address to bootstrap from, `None` if the host is a DNS name that has to be resolved
+    /// first, see [`Endpoint::resolve`].
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match &self.host {
+            Host::Ip(ip) => Some(SocketAddr::new(*ip, self.port())),
+            Host::Domain(_) => None,
+        }
+    }
+
+    /// All addresses to bootstrap from, resolving the host if it is a DNS name.
+    pub fn resolve(&self) -> std::io::Result<Vec<SocketAddr>> {
+        match &self.host {
+            Host::Ip(ip) => Ok(vec![SocketAddr::new(*ip, self.port())]),
+            Host::Domain(name) => Ok((name.as_str(), self.port()).to_socket_addrs()?.collect()),
+        }
+    }
+}
+
+/// `endpoints` in the order a client should try them: by ascending priority, and within the same
+/// priority in a random order where each endpoint comes first with a probability proportional to
+/// its weight. A new order is drawn on every call.
+pub fn failover_order(endpoints: &[Endpoint]) -> Vec<&Endpoint> {
+    let state = RandomState::new();
+    let mut counter = 0u64;
+    failover_order_with(endpoints, || {
+        counter += 1;
+        let mut hasher = state.build_hasher();
+        hasher.write_u64(counter);
+        hasher.finish()
+    })
+}
+
+/// [`failover_order`] drawing from `random`, which has to return uniformly distributed values.
+pub fn failover_order_with(
+    endpoints: &[Endpoint],
+    mut random: impl FnMut() -> u64,
+) -> Vec<&Endpoint> {
+    let mut remaining: Vec<&Endpoint> = endpoints.iter().collect();
+    // stable, so endpoints with a weight of 0 stay in config order
+    remaining.sort_by_key(|endpoint| endpoint.priority);
+
+    let mut ordered = Vec::with_capacity(remaining.len());
+    while let Some(first) = remaining.first() {
+        let group_len = remaining
+            .iter()
+            .take_while(|endpoint| endpoint.priority == first.priority)
+            .count();
+        let total: u64 = remaining[..group_len]
+            .iter()
+            .map(|endpoint| u64::from(endpoint.weight))
+            .sum();
+
+        let pick = if total == 0 {
+            0
+        } else {
+            let mut draw = random() % total;
+            remaining[..group_len]
+                .iter()
+                .position(|endpoint| {
+                    let weight = u64::from(endpoint.weight);
+                    let hit = draw < weight;
+                    draw = draw.saturating_sub(weight);
+                    hit
+                })
+                .unwrap_or(0)
+        };
+        ordered.push(remaining.remove(pick));
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(host: &str, priority: u32, weight: u32) -> Endpoint {
+        Endpoint {
+            priority,
+            weight,
+            ..Endpoint::new(host.parse().unwrap(), None)
+        }
+    }
+
+    fn hosts(endpoints: Vec<&Endpoint>) -> Vec<String> {
+        endpoints.iter().map(|e| e.host.to_string()).collect()
+    }
+
+    #[test]
+    fn ordered_by_priority_then_weight() {
+        let endpoints = [
+            endpoint("192.0.2.3", 1, 1),
+            endpoint("192.0.2.1", 0, 1),
+            endpoint("192.0.2.2", 0, 3),
+            endpoint("192.0.2.4", 1, 0),
+        ];
+
+        // a draw of 0 picks the first endpoint of a priority, and 1 falls in the weight of the
+        // second when the first has a weight of 1
+        let mut draws = [0, 0, 0].into_iter();
+        assert_eq!(
+            hosts(failover_order_with(&endpoints, || draws.next().unwrap())),
+            ["192.0.2.1", "192.0.2.2", "192.0.2.3", "192.0.2.4"]
+        );
+        let mut draws = [1, 0, 0].into_iter();
+        assert_eq!(
+            hosts(failover_order_with(&endpoints, || draws.next().unwrap())),
+            ["192.0.2.2", "192.0.2.1", "192.0.2.3", "192.0.2.4"]
+        );
+
+        let mut firsts = [0; 2];
+        for _ in 0..1000 {
+            let order = failover_order(&endpoints);
+            assert_eq!(order.len(), endpoints.len());
+            assert_eq!(order[0].priority, 0);
+            assert_eq!(order[1].priority, 0);
+            assert_eq!(hosts(order[2..].to_vec()), ["192.0.2.3", "192.0.2.4"]);
+            firsts[usize::from(order[0].weight == 3)] += 1;
+        }
+        // the endpoint with weight 3 comes first about 750 times
+        assert!(firsts[1] > 600 && firsts[0] > 100, "{firsts:?}");
+    }
+}
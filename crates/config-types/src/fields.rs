@@ -0,0 +1,210 @@
+//! Finding fields in a config that [`BaseConfig`](crate::BaseConfig) has no place for.
+//!
+//! Unknown fields are ignored when a config is parsed, so a typo like `prority` silently drops
+//! the value. Build scripts report them, as errors in strict mode and as warnings otherwise.
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use toml::{Table, Value};
+
+use std::fmt;
+
+use crate::{migrate, Endpoint, RawBaseConfig};
+
+/// A field in a config that is not part of the layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownField {
+    /// Where the field is, e.g. `endpoint[0].prority`.
+    pub path: String,
+    /// The valid field name closest to the unknown one, if there is one close enough to be a
+    /// likely typo.
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown field `{}`", self.path)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean `{suggestion}`?")?;
+        }
+        Ok(())
+    }
+}
+
+/// Every unknown field in `config`, after migrating it to the current layout.
+///
+/// `[keys]` takes any name, so a key there is only reported when it is not one of `required_keys`
+/// but close to one that is missing, e.g. `githb` without `github`.
+pub fn unknown_fields(config: &str, required_keys: &[&str]) -> Result<Vec<UnknownField>, String> {
+    let mut config: Table = toml::from_str(config).map_err(|e| e.to_string())?;
+    migrate(&mut config)?;
+
+    let mut unknown = Vec::new();
+    check_table(&config, "", struct_fields::<RawBaseConfig>(), &mut unknown);
+
+    if let Some(Value::Table(keys)) = config.get("keys") {
+        let missing: Vec<&str> = required_keys
+            .iter()
+            .copied()
+            .filter(|name| !keys.contains_key(*name))
+            .collect();
+        for (name, key) in keys {
+            if let Some(suggestion) = closest(name, &missing) {
+                unknown.push(UnknownField {
+                    path: format!("keys.{name}"),
+                    suggestion: Some(suggestion.to_string()),
+                });
+            }
+            if let Value::Table(key) = key {
+                let path = format!("keys.{name}.");
+                check_table(key, &path, crate::keys::table_fields(), &mut unknown);
+            }
+        }
+    }
+
+    if let Some(Value::Array(endpoints)) = config.get("endpoint") {
+        for (i, endpoint) in endpoints.iter().enumerate() {
+            if let Value::Table(endpoint) = endpoint {
+                let path = format!("endpoint[{i}].");
+                check_table(endpoint, &path, struct_fields::<Endpoint>(), &mut unknown);
+            }
+        }
+    }
+
+    Ok(unknown)
+}
+
+fn check_table(
+    table: &Table,
+    prefix: &str,
+    fields: &'static [&'static str],
+    unknown: &mut Vec<UnknownField>,
+) {
+    for name in table.keys() {
+        if !fields.contains(&name.as_str()) {
+            unknown.push(UnknownField {
+                path: format!("{prefix}{name}"),
+                suggestion: closest(name, fields).map(str::to_string),
+            });
+        }
+    }
+}
+
+/// The candidate closest to `name`, if it is within a quarter of its length in edits.
+fn closest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|candidate| (strsim::damerau_levenshtein(name, candidate), *candidate))
+        .filter(|(distance, candidate)| *distance <= (candidate.len() / 4).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The field names a derived `Deserialize` impl of a struct accepts, as they are written in a
+/// config, so that the lists here cannot drift from the structs themselves.
+pub(crate) fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    struct Fields(&'static [&'static str]);
+
+    impl fmt::Display for Fields {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("not a struct")
+        }
+    }
+
+    impl fmt::Debug for Fields {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("not a struct")
+        }
+    }
+
+    impl std::error::Error for Fields {}
+
+    impl de::Error for Fields {
+        fn custom<M: fmt::Display>(_: M) -> Self {
+            Fields(&[])
+        }
+    }
+
+    // answers the request of the struct for its fields with an error that carries them
+    struct Introspect;
+
+    impl<'de> Deserializer<'de> for Introspect {
+        type Error = Fields;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Fields> {
+            Err(Fields(&[]))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _: &'static str,
+            fields: &'static [&'static str],
+            _: V,
+        ) -> Result<V::Value, Fields> {
+            Err(Fields(fields))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    match T::deserialize(Introspect) {
+        Ok(_) => &[],
+        Err(Fields(fields)) => fields,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typos_are_suggested() {
+        let config = r#"verison = 1
+
+[keys]
+githb = "xxxxxxxxxxxxxxxxx"
+gitlab = "zzzzzzzzzzzzzzzzz"
+travis = { secret = "yyyyyyyyyyyyyyyyy", scop = "read:builds" }
+
+[[endpoint]]
+host = "127.0.0.1"
+prority = 1
+location = "eu-west"
+"#;
+        let unknown: Vec<String> = unknown_fields(config, &["github"])
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            unknown,
+            [
+                "unknown field `verison`, did you mean `version`?",
+                "unknown field `keys.githb`, did you mean `github`?",
+                "unknown field `keys.travis.scop`, did you mean `scope`?",
+                "unknown field `endpoint[0].location`",
+                "unknown field `endpoint[0].prority`, did you mean `priority`?",
+            ]
+        );
+
+        // fields that moved in a migration are not unknown
+        let legacy = "ip = \"127.0.0.1\"\nport = 1\n[keys]\ngithub = \"x\"\ngitlab = \"z\"\n";
+        assert_eq!(unknown_fields(legacy, &["github"]), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn fields_of_structs() {
+        assert_eq!(
+            struct_fields::<RawBaseConfig>(),
+            ["version", "keys", "endpoint"]
+        );
+        assert_eq!(
+            struct_fields::<Endpoint>(),
+            ["host", "port", "priority", "weight", "region"]
+        );
+        assert_eq!(struct_fields::<u32>(), [] as [&str; 0]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Hosts a client can bootstrap from.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// An IP address or a DNS name, validated when it is parsed so that a config naming something
+/// that is neither fails to load.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Host {
+    Ip(IpAddr),
+    /// A DNS name made of labels of ASCII letters, digits, and `-`, as in RFC 1123. The top
+    /// level label may not be numeric, so that a mistyped IPv4 address is not taken as a name.
+    Domain(String),
+}
+
+/// Why a string is not a [`Host`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidHost(String);
+
+impl fmt::Display for InvalidHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid host `{}`, expected an IP address or a DNS name",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidHost {}
+
+impl FromStr for Host {
+    type Err = InvalidHost;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(ip) = s.parse() {
+            return Ok(Host::Ip(ip));
+        }
+
+        let name = s.strip_suffix('.').unwrap_or(s);
+        let valid_label = |label: &str| {
+            (1..=63).contains(&label.len())
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+        };
+        let numeric_tld = name
+            .rsplit('.')
+            .next()
+            .is_some_and(|tld| tld.bytes().all(|b| b.is_ascii_digit()));
+
+        if name.len() <= 253 && name.split('.').all(valid_label) && !numeric_tld {
+            Ok(Host::Domain(s.to_string()))
+        } else {
+            Err(InvalidHost(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Host::Ip(ip) => ip.fmt(f),
+            Host::Domain(name) => f.write_str(name),
+        }
+    }
+}
+
+impl From<IpAddr> for Host {
+    fn from(ip: IpAddr) -> Self {
+        Host::Ip(ip)
+    }
+}
+
+impl Serialize for Host {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Host {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "127.0.0.1".parse(),
+            Ok(Host::Ip("127.0.0.1".parse().unwrap()))
+        );
+        assert_eq!("::1".parse(), Ok(Host::Ip("::1".parse().unwrap())));
+        for name in [
+            "localhost",
+            "bootstrap-1.nymvpn.example",
+            "example.com.",
+            "1.example",
+        ] {
+            assert_eq!(name.parse(), Ok(Host::Domain(name.to_string())));
+        }
+
+        let long_label = "a".repeat(64);
+        for invalid in [
+            "",
+            "not an ip",
+            "256.0.0.1",
+            "127.0.0.1:4433",
+            "-bootstrap.example",
+            "bootstrap..example",
+            "bootstrap_1.example",
+            long_label.as_str(),
+        ] {
+            assert!(invalid.parse::<Host>().is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! The `[keys]` table, named API keys for third-party services.
+
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use toml::value::Datetime;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::Secret;
+
+/// Named API keys. Any name can be used, so a new service needs no change to this crate.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(transparent)]
+pub struct Keys(BTreeMap<String, Key>);
+
+/// An API key and what is known about it.
+///
+/// In a config it is either just the key, or a table with the key in `secret` and the optional
+/// metadata next to it:
+///
+/// ```toml
+/// [keys]
+/// github = "..."
+/// travis = { secret = "...", scope = "read:builds", expires = 2027-01-01 }
+/// ```
+#[derive(Clone)]
+pub struct Key {
+    pub secret: Secret<String>,
+    /// What the key grants access to, as named by the service.
+    pub scope: Option<String>,
+    /// When the key stops working.
+    pub expires: Option<Datetime>,
+}
+
+impl Keys {
+    /// The secret of the key called `name`.
+    pub fn get(&self, name: &str) -> Option<&Secret<String>> {
+        self.0.get(name).map(|key| &key.secret)
+    }
+
+    /// The key called `name` along with its metadata.
+    pub fn key(&self, name: &str) -> Option<&Key> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, key: impl Into<Key>) {
+        self.0.insert(name.into(), key.into());
+    }
+
+    /// All keys, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Key)> {
+        self.0.iter().map(|(name, key)| (name.as_str(), key))
+    }
+
+    /// The same keys with each secret replaced by the placeholder [`Secret`] prints, for showing a
+    /// config without giving its keys away.
+    pub(crate) fn redacted(&self) -> Keys {
+        let keys = self.0.iter().map(|(name, key)| {
+            let key = Key {
+                secret: Secret::new(key.secret.to_string()),
+                scope: key.scope.clone(),
+                expires: key.expires,
+            };
+            (name.clone(), key)
+        });
+        Keys(keys.collect())
+    }
+
+    /// Check that there is a key for each of `names`, the keys a crate cannot work without.
+    pub fn require(&self, names: &[&str]) -> Result<(), String> {
+        let missing: Vec<String> = names
+            .iter()
+            .filter(|name| !self.0.contains_key(**name))
+            .map(|name| format!("`{name}`"))
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "missing required keys in [keys]: {}",
+                missing.join(", ")
+            ))
+        }
+    }
+}
+
+impl Key {
+    /// A key without metadata.
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self {
+            secret: Secret::new(secret.into()),
+            scope: None,
+            expires: None,
+        }
+    }
+}
+
+impl From<&str> for Key {
+    fn from(secret: &str) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<String> for Key {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+/// A [`Key`] written as a table.
+#[derive(Deserialize, Serialize)]
+struct KeyTable {
+    secret: Secret<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires: Option<Datetime>,
+}
+
+/// The fields of a key written as a table.
+pub(crate) fn table_fields() -> &'static [&'static str] {
+    crate::fields::struct_fields::<KeyTable>()
+}
+
+// keys without metadata are written as plain strings, so that configs which do not use metadata
+// keep their canonical form, and their signatures.
+impl Serialize for Key {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.scope.is_none() && self.expires.is_none() {
+            return self.secret.serialize(serializer);
+        }
+        KeyTable {
+            secret: self.secret.clone(),
+            scope: self.scope.clone(),
+            expires: self.expires,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an API key, or a table with the key in `secret`")
+            }
+
+            fn visit_str<E: de::Error>(self, secret: &str) -> Result<Key, E> {
+                Ok(Key::new(secret))
+            }
+
+            fn visit_string<E: de::Error>(self, secret: String) -> Result<Key, E> {
+                Ok(Key::new(secret))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Key, A::Error> {
+                let table = KeyTable::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                Ok(Key {
+                    secret: table.secret,
+                    scope: table.scope,
+                    expires: table.expires,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(KeyVisitor)
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Key")
+            .field("secret", &self.secret)
+            .field("scope", &self.scope)
+            .field("expires", &self.expires)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &str = r#"github = "xxxxxxxxxxxxxxxxx"
+
+[travis]
+secret = "yyyyyyyyyyyyyyyyy"
+scope = "read:builds"
+expires = 2027-01-01
+"#;
+
+    #[test]
+    fn named_keys() {
+        let keys: Keys = toml::from_str(KEYS).unwrap();
+        assert_eq!(keys.get("github").unwrap().expose(), "xxxxxxxxxxxxxxxxx");
+        assert_eq!(keys.get("travis").unwrap().expose(), "yyyyyyyyyyyyyyyyy");
+        assert!(keys.get("gitlab").is_none());
+
+        let github = keys.key("github").unwrap();
+        assert_eq!((&github.scope, &github.expires), (&None, &None));
+        let travis = keys.key("travis").unwrap();
+        assert_eq!(travis.scope.as_deref(), Some("read:builds"));
+        assert_eq!(travis.expires.unwrap().to_string(), "2027-01-01");
+        assert!(!format!("{travis:?}").contains("yyyy"));
+
+        assert_eq!(toml::to_string(&keys).unwrap(), KEYS);
+        let value = toml::Value::try_from(&keys).unwrap();
+        let keys: Keys = value.try_into().unwrap();
+        assert_eq!(keys.iter().count(), 2);
+    }
+
+    #[test]
+    fn required_keys() {
+        let mut keys = Keys::default();
+        assert_eq!(
+            keys.require(&["github", "travis"]),
+            Err("missing required keys in [keys]: `github`, `travis`".to_string())
+        );
+        keys.insert("github", "xxxxxxxxxxxxxxxxx");
+        assert!(keys.require(&["github"]).is_ok());
+
+        let err = toml::from_str::<Keys>("github = 1").err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("expected an API key, or a table with the key in `secret`"),
+            "{err}"
+        );
+        let err = toml::from_str::<Keys>("github = { scope = \"repo\" }")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("missing field `secret`"), "{err}");
+    }
+}
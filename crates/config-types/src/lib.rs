@@ -1,82 +1,415 @@
+pub mod canonical;
+pub mod endpoint;
+pub mod fields;
+pub mod host;
+pub mod keys;
+pub mod migrate;
+pub mod patch;
+pub mod secret;
+
+pub use canonical::{canonical_sha256, canonical_toml};
+pub use endpoint::{Endpoint, DEFAULT_PORT};
+pub use fields::{unknown_fields, UnknownField};
+pub use host::Host;
+pub use keys::{Key, Keys};
+pub use migrate::{migrate, CONFIG_VERSION};
+pub use secret::Secret;
+
+use ed25519_dalek::{Signature, SignatureError, VerifyingKey};
 use serde::{Deserialize, Serialize};
 
+/// A bootstrap config in the layout of [`CONFIG_VERSION`]. Configs written for older layouts
+/// are upgraded by [`migrate`] when they are read with [`BaseConfig::from_str`].
 #[derive(Deserialize, Serialize, Clone)]
+#[serde(try_from = "RawBaseConfig")]
 pub struct BaseConfig {
-    pub ip: String,
-    pub port: Option<u16>,
+    /// The layout version of the config, see [`migrate`].
+    pub version: u32,
     pub keys: Keys,
+    #[serde(rename = "endpoint")]
+    pub endpoints: Vec<Endpoint>,
 }
 
-#[derive(Deserialize, Serialize, Clone)]
-pub struct Keys {
-    pub github: String,
-    pub travis: Option<String>,
+/// [`BaseConfig`] as written, before checking its version and that it has an endpoint.
+#[derive(Deserialize)]
+struct RawBaseConfig {
+    #[serde(default)]
+    version: u32,
+    keys: Keys,
+    #[serde(rename = "endpoint", default)]
+    endpoints: Vec<Endpoint>,
+}
+
+impl TryFrom<RawBaseConfig> for BaseConfig {
+    type Error = String;
+
+    fn try_from(raw: RawBaseConfig) -> Result<Self, Self::Error> {
+        if raw.version != CONFIG_VERSION {
+            return Err(format!(
+                "config version {} does not match version {CONFIG_VERSION} this build reads, \
+                configs have to be migrated with `config_types::migrate` before they are parsed",
+                raw.version
+            ));
+        }
+        if raw.endpoints.is_empty() {
+            return Err("no bootstrap endpoint, add an `[[endpoint]]`".to_string());
+        }
+
+        Ok(Self {
+            version: raw.version,
+            keys: raw.keys,
+            endpoints: raw.endpoints,
+        })
+    }
 }
 
 impl std::str::FromStr for BaseConfig {
     type Err = toml::de::Error;
+
+    /// Parse a config, first migrating it to the current layout if it was written for an older
+    /// one.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        toml::from_str(s)
+        let mut table: toml::Table = toml::from_str(s)?;
+        let applied = migrate(&mut table).map_err(serde::de::Error::custom)?;
+        if applied.is_empty() {
+            // parsed again from the text rather than the table, so that errors point into it
+            toml::from_str(s)
+        } else {
+            toml::Value::Table(table).try_into()
+        }
+    }
+}
+
+impl BaseConfig {
+    /// The endpoints in the order a client should try them until one answers, see
+    /// [`endpoint::failover_order`]. A new order is drawn on every call, so that clients spread
+    /// over the endpoints with the same priority.
+    pub fn failover_order(&self) -> Vec<&Endpoint> {
+        endpoint::failover_order(&self.endpoints)
+    }
+
+    /// The config as toml, secrets included, for writing it back out. `Display` redacts the
+    /// secrets instead.
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    /// The [`canonical_toml`] form of the config, which config signatures and the provenance
+    /// hash cover. Like [`BaseConfig::to_toml`] it serializes the secrets themselves, and never
+    /// goes through `Display`.
+    pub fn canonical_toml(&self) -> Result<String, toml::ser::Error> {
+        canonical_toml(self)
+    }
+
+    /// Check a detached Ed25519 signature over the canonical form of the config against the
+    /// public key `key`.
+    pub fn verify_signature(&self, key: &[u8; 32], signature: &[u8]) -> Result<(), SignatureError> {
+        let key = VerifyingKey::from_bytes(key)?;
+        let signature = Signature::from_slice(signature)?;
+        let canonical = self.canonical_toml().map_err(|_| SignatureError::new())?;
+        key.verify_strict(canonical.as_bytes(), &signature)
     }
 }
 
-impl ToString for BaseConfig {
-    fn to_string(&self) -> String {
-        toml::to_string(self).unwrap()
+/// The config as toml with every secret redacted, the way `Debug` prints a [`Secret`], so that
+/// printing a config cannot leak its keys. Use [`BaseConfig::to_toml`] for the secrets.
+impl std::fmt::Display for BaseConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redacted = BaseConfig {
+            version: self.version,
+            keys: self.keys.redacted(),
+            endpoints: self.endpoints.clone(),
+        };
+        f.write_str(&redacted.to_toml().map_err(|_| std::fmt::Error)?)
     }
 }
 
+/// Which config a client was built with, without exposing the config itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// Version of the crate that embedded the config.
+    pub version: &'static str,
+    /// SHA-256 of the embedded config in canonical form, as recorded in the provenance manifest
+    /// written by the build. `None` if no config was embedded and the in-source default is used.
+    pub config_sha256: Option<&'static str>,
+    features: &'static str,
+}
+
+impl BuildInfo {
+    /// `features` is a comma separated list and an empty `config_sha256` means no config was
+    /// embedded, matching the environment variables set by the build script.
+    pub const fn new(
+        version: &'static str,
+        features: &'static str,
+        config_sha256: &'static str,
+    ) -> Self {
+        Self {
+            version,
+            config_sha256: if config_sha256.is_empty() {
+                None
+            } else {
+                Some(config_sha256)
+            },
+            features,
+        }
+    }
+
+    /// The features the crate that embedded the config was built with.
+    pub fn features(&self) -> impl Iterator<Item = &'static str> {
+        self.features
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+    }
+}
+
+/// Keys the client cannot work without. The bootstraps build scripts fail the build of a config
+/// that lacks any of them, and `nymcfg` refuses to seal one.
+pub const REQUIRED_KEYS: &[&str] = &["github"];
+
+/// Public half of the development key config files are signed with. Its secret half is
+/// [`DEV_CONFIG_SIGNING_SECRET`], so anyone can sign a config with it. Build scripts only fall
+/// back to it when no key is given in the build environment, and release builds refuse it, see
+/// `bootstrap_build::Bootstrap::verifying_key`.
+pub const DEV_CONFIG_SIGNING_KEY: [u8; 32] = [
+    0x64, 0x66, 0x88, 0xe3, 0x95, 0x04, 0x51, 0x66, 0x0f, 0x7b, 0x11, 0x24, 0x37, 0x82, 0xa6, 0x83,
+    0x31, 0x2b, 0x79, 0x83, 0xbd, 0x9f, 0xed, 0xe9, 0xe4, 0x43, 0x06, 0x3f, 0x0e, 0x5c, 0x10, 0xaa,
+];
+
+/// Secret half of [`DEV_CONFIG_SIGNING_KEY`], for tests that sign configs. Only available with
+/// the `dev-signing-key` feature, which is meant for dev-dependencies.
+#[cfg(feature = "dev-signing-key")]
+pub const DEV_CONFIG_SIGNING_SECRET: [u8; 32] = [
+    0x6f, 0xd2, 0xb4, 0x5f, 0x62, 0x25, 0xb1, 0x31, 0xda, 0xb8, 0xee, 0x48, 0xaf, 0x24, 0xc5, 0x75,
+    0xa2, 0xf9, 0x8c, 0x06, 0x6b, 0x65, 0x44, 0x93, 0xd2, 0x0b, 0x88, 0x45, 0xb9, 0x6e, 0xfe, 0x50,
+];
+
+/// Parse an Ed25519 public key written as 64 hex digits, the way build scripts are given the key
+/// configs are signed with and pass it on to the crate being built.
+pub fn verifying_key_from_hex(hex: &str) -> Result<[u8; 32], String> {
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!(
+            "expected 64 hex digits of an Ed25519 public key, found `{hex}`"
+        ));
+    }
+
+    let mut key = [0; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+    }
+    Ok(key)
+}
+
+/// Rebuild a string that was masked at compile time, see `bootstrap_build::emit::Masked`, so that
+/// no string from the config is ever stored contiguously in the binary.
+pub fn unmask(masked: &[u8], mask: &[u8]) -> String {
+    // black_box keeps the compiler from unmasking the constant strings at compile time.
+    let mask = std::hint::black_box(mask);
+    let bytes = masked.iter().zip(mask).map(|(b, m)| b ^ m).collect();
+    String::from_utf8(bytes).expect("masked config string is not valid utf-8")
+}
+
 pub const DEFAULT_CONFIG_TOML_STR: &str = r#"
-ip = "192.168.1.1"
-port = 4433
+version = 1
 
 [keys]
 github = "00000000000000000"
 travis = "11111111111111111"
+
+[[endpoint]]
+host = "192.168.1.1"
+port = 4433
 "#;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::net::SocketAddr;
     use std::str::FromStr;
 
-    const TEST_CONFIG: &str = r#"ip = "127.0.0.1"
+    const TEST_CONFIG: &str = r#"version = 1
+
+[keys]
+github = "xxxxxxxxxxxxxxxxx"
+travis = "yyyyyyyyyyyyyyyyy"
+
+[[endpoint]]
+host = "127.0.0.1"
+priority = 0
+weight = 1
+"#;
+
+    /// [`TEST_CONFIG`] as it was written before the layout was versioned.
+    const LEGACY_CONFIG: &str = r#"ip = "127.0.0.1"
 
 [keys]
 github = "xxxxxxxxxxxxxxxxx"
 travis = "yyyyyyyyyyyyyyyyy"
+"#;
+
+    const ENDPOINTS: &str = r#"version = 1
+
+[keys]
+github = "xxxxxxxxxxxxxxxxx"
+
+[[endpoint]]
+host = "localhost"
+port = 1234
+priority = 1
+
+[[endpoint]]
+host = "192.0.2.1"
+weight = 5
+region = "eu-west"
 "#;
 
     #[test]
     fn deserialize() {
-        let config: BaseConfig = toml::from_str(TEST_CONFIG).unwrap();
-        assert_eq!(config.ip, "127.0.0.1");
-        assert_eq!(config.port, None);
-        assert_eq!(config.keys.github, "xxxxxxxxxxxxxxxxx");
-        assert_eq!(config.keys.travis.as_ref().unwrap(), "yyyyyyyyyyyyyyyyy");
+        for config in [
+            toml::from_str(TEST_CONFIG).unwrap(),
+            BaseConfig::from_str(TEST_CONFIG).unwrap(),
+            BaseConfig::from_str(LEGACY_CONFIG).unwrap(),
+        ] {
+            assert_eq!(config.version, CONFIG_VERSION);
+            assert_eq!(
+                config.endpoints,
+                [Endpoint::new("127.0.0.1".parse().unwrap(), None)]
+            );
+            assert_eq!(
+                config.keys.get("github").unwrap().expose(),
+                "xxxxxxxxxxxxxxxxx"
+            );
+            assert_eq!(
+                config.keys.get("travis").unwrap().expose(),
+                "yyyyyyyyyyyyyyyyy"
+            );
+        }
 
-        let config = BaseConfig::from_str(TEST_CONFIG).unwrap();
-        assert_eq!(config.ip, "127.0.0.1");
-        assert_eq!(config.port, None);
-        assert_eq!(config.keys.github, "xxxxxxxxxxxxxxxxx");
-        assert_eq!(config.keys.travis.as_ref().unwrap(), "yyyyyyyyyyyyyyyyy");
+        // only `from_str` migrates
+        let err = toml::from_str::<BaseConfig>(LEGACY_CONFIG).err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("config version 0 does not match version 1"),
+            "{err}"
+        );
     }
 
     #[test]
     fn serialize() {
-        let config = BaseConfig {
-            ip: "127.0.0.1".to_string(),
-            port: None,
-            keys: Keys {
-                github: "xxxxxxxxxxxxxxxxx".to_string(),
-                travis: Some("yyyyyyyyyyyyyyyyy".to_string()),
-            },
+        let mut config = BaseConfig {
+            version: CONFIG_VERSION,
+            keys: Keys::default(),
+            endpoints: vec![Endpoint::new("127.0.0.1".parse().unwrap(), None)],
         };
+        config.keys.insert("github", "xxxxxxxxxxxxxxxxx");
+        config.keys.insert("travis", "yyyyyyyyyyyyyyyyy");
 
         let serialized_toml = toml::to_string(&config).unwrap();
         assert_eq!(serialized_toml, TEST_CONFIG);
 
-        let serialized_toml = config.to_string();
+        let serialized_toml = config.to_toml().unwrap();
         assert_eq!(serialized_toml, TEST_CONFIG);
+
+        let migrated = BaseConfig::from_str(LEGACY_CONFIG).unwrap();
+        assert_eq!(migrated.to_toml().unwrap(), TEST_CONFIG);
+
+        // printing a config does not give its keys away
+        let printed = config.to_string();
+        assert_eq!(
+            printed,
+            TEST_CONFIG
+                .replace("xxxxxxxxxxxxxxxxx", "[REDACTED]")
+                .replace("yyyyyyyyyyyyyyyyy", "[REDACTED]")
+        );
+    }
+
+    #[test]
+    fn endpoints() {
+        let config = BaseConfig::from_str(TEST_CONFIG).unwrap();
+        assert_eq!(
+            config.failover_order()[0].socket_addr(),
+            Some(SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT)))
+        );
+
+        let config = BaseConfig::from_str(ENDPOINTS).unwrap();
+        let endpoints = &config.endpoints;
+        assert_eq!(endpoints.len(), 2);
+        assert_eq!(endpoints[0].host, Host::Domain("localhost".to_string()));
+        assert_eq!(endpoints[0].port(), 1234);
+        assert_eq!(endpoints[0].socket_addr(), None);
+        assert_eq!((endpoints[0].priority, endpoints[0].weight), (1, 1));
+        assert_eq!(endpoints[1].region.as_deref(), Some("eu-west"));
+        assert_eq!((endpoints[1].priority, endpoints[1].weight), (0, 5));
+        assert_eq!(config.failover_order(), [&endpoints[1], &endpoints[0]]);
+
+        let round_trip = BaseConfig::from_str(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(&round_trip.endpoints, endpoints);
+    }
+
+    #[test]
+    fn invalid_endpoints() {
+        for (config, expected) in [
+            (
+                TEST_CONFIG.replace("127.0.0.1", "not an ip"),
+                "invalid host `not an ip`",
+            ),
+            (
+                LEGACY_CONFIG.replace("127.0.0.1", "not an ip"),
+                "invalid host `not an ip`",
+            ),
+            (
+                TEST_CONFIG[..TEST_CONFIG.find("[[endpoint]]").unwrap()].to_string(),
+                "no bootstrap endpoint",
+            ),
+            (
+                LEGACY_CONFIG.replace("ip = \"127.0.0.1\"", "port = 1"),
+                "`port` is only used with `ip`",
+            ),
+            (
+                TEST_CONFIG.replace("version = 1", "version = 2"),
+                "config version 2 is newer than the newest version this build reads, 1",
+            ),
+        ] {
+            let err = BaseConfig::from_str(&config).err().unwrap();
+            assert!(err.to_string().contains(expected), "{err}");
+        }
+    }
+
+    #[test]
+    fn signature() {
+        // the example config in the workspace root is signed with the development key
+        let signature = include_bytes!("../../../nymvpn-config.toml.sig");
+        let mut config = BaseConfig::from_str(TEST_CONFIG).unwrap();
+        let key = &DEV_CONFIG_SIGNING_KEY;
+        assert!(config.verify_signature(key, signature).is_ok());
+        assert!(config.verify_signature(key, &signature[1..]).is_err());
+        assert!(config.verify_signature(&[7; 32], signature).is_err());
+
+        config.endpoints[0].host = "192.0.2.1".parse().unwrap();
+        assert!(config.verify_signature(key, signature).is_err());
+    }
+
+    #[cfg(feature = "dev-signing-key")]
+    #[test]
+    fn dev_signing_secret() {
+        let key = ed25519_dalek::SigningKey::from_bytes(&DEV_CONFIG_SIGNING_SECRET);
+        assert_eq!(key.verifying_key().to_bytes(), DEV_CONFIG_SIGNING_KEY);
+    }
+
+    #[test]
+    fn verifying_key_hex() {
+        let hex: String = DEV_CONFIG_SIGNING_KEY
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        assert_eq!(verifying_key_from_hex(&hex), Ok(DEV_CONFIG_SIGNING_KEY));
+        assert_eq!(
+            verifying_key_from_hex(&hex.to_uppercase()),
+            Ok(DEV_CONFIG_SIGNING_KEY)
+        );
+
+        assert!(verifying_key_from_hex(&hex[2..]).is_err());
+        assert!(verifying_key_from_hex(&hex.replacen('6', "g", 1)).is_err());
+        assert!(verifying_key_from_hex(&hex.replacen("64", "+4", 1)).is_err());
     }
 }
@@ -0,0 +1,153 @@
+//! Upgrading configs written for older layouts of [`BaseConfig`](crate::BaseConfig).
+//!
+//! Configs live outside the repository and are not updated along with the code, so every change
+//! to the layout that older configs do not parse with bumps [`CONFIG_VERSION`] and adds a
+//! [`Migration`] to [`MIGRATIONS`] that rewrites a config of the previous version.
+
+use toml::{Table, Value};
+
+use std::fmt;
+
+/// The version of the current layout, written to the `version` field of a config.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// A rewrite of a config of one version into the layout of the next.
+pub struct Migration {
+    /// The version this migration upgrades from, to `from + 1`.
+    pub from: u32,
+    /// What changed, for the person who has to update the config.
+    pub description: &'static str,
+    apply: fn(&mut Table) -> Result<(), String>,
+}
+
+/// Every migration, ordered by the version they upgrade from.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "`ip` and `port` moved into an `[[endpoint]]` entry",
+    apply: endpoint_list,
+}];
+
+impl fmt::Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "version {} to {}: {}",
+            self.from,
+            self.from + 1,
+            self.description
+        )
+    }
+}
+
+/// The version of `config`. Configs written before the layout was versioned have no `version`
+/// field and are version 0.
+pub fn version(config: &Table) -> Result<u32, String> {
+    match config.get("version") {
+        None => Ok(0),
+        Some(Value::Integer(version)) => {
+            u32::try_from(*version).map_err(|_| format!("invalid config version {version}"))
+        }
+        Some(version) => Err(format!(
+            "invalid config version {version}, expected an integer"
+        )),
+    }
+}
+
+/// Upgrade `config` to [`CONFIG_VERSION`], returning the migrations that were applied, in order.
+/// A config that is already current is left as it is.
+pub fn migrate(config: &mut Table) -> Result<Vec<&'static Migration>, String> {
+    let mut version = version(config)?;
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "config version {version} is newer than the newest version this build reads, \
+            {CONFIG_VERSION}"
+        ));
+    }
+
+    let mut applied = Vec::new();
+    while version < CONFIG_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or_else(|| format!("no migration from config version {version}"))?;
+        (migration.apply)(config)
+            .map_err(|e| format!("failed to migrate config from version {version}: {e}"))?;
+        applied.push(migration);
+        version += 1;
+    }
+
+    if !applied.is_empty() {
+        config.insert("version".to_string(), Value::Integer(version.into()));
+    }
+    Ok(applied)
+}
+
+/// Version 0 to 1: a single top-level `ip`, with an optional `port`, becomes the only
+/// `[[endpoint]]`. Version 0 configs that already list their endpoints are left as they are.
+fn endpoint_list(config: &mut Table) -> Result<(), String> {
+    let Some(ip) = config.remove("ip") else {
+        if config.contains_key("port") {
+            return Err("`port` is only used with `ip`".to_string());
+        }
+        return Ok(());
+    };
+    if config.contains_key("endpoint") {
+        return Err("`ip` and `[[endpoint]]` cannot both be set".to_string());
+    }
+
+    let mut endpoint = Table::new();
+    endpoint.insert("host".to_string(), ip);
+    if let Some(port) = config.remove("port") {
+        endpoint.insert("port".to_string(), port);
+    }
+    config.insert(
+        "endpoint".to_string(),
+        Value::Array(vec![Value::Table(endpoint)]),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_ip_to_endpoint_list() {
+        let mut config: Table =
+            toml::from_str("ip = \"192.0.2.1\"\nport = 1234\n[keys]\ngithub = \"x\"\n").unwrap();
+        let applied = migrate(&mut config).unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(
+            applied[0].to_string(),
+            "version 0 to 1: `ip` and `port` moved into an `[[endpoint]]` entry"
+        );
+
+        let expected: Table = toml::from_str(
+            "version = 1\n[keys]\ngithub = \"x\"\n\
+            [[endpoint]]\nhost = \"192.0.2.1\"\nport = 1234\n",
+        )
+        .unwrap();
+        assert_eq!(config, expected);
+
+        // current configs are left alone
+        assert!(migrate(&mut config).unwrap().is_empty());
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn unmigratable() {
+        for (config, expected) in [
+            ("version = 2", "config version 2 is newer"),
+            ("version = \"1\"", "invalid config version \"1\""),
+            ("port = 1", "`port` is only used with `ip`"),
+            (
+                "ip = \"192.0.2.1\"\n[[endpoint]]\nhost = \"192.0.2.2\"",
+                "`ip` and `[[endpoint]]` cannot both be set",
+            ),
+        ] {
+            let mut config: Table = toml::from_str(config).unwrap();
+            let err = migrate(&mut config).err().unwrap();
+            assert!(err.contains(expected), "{err}");
+        }
+    }
+}
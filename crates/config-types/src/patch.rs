@@ -0,0 +1,145 @@
+//! Layout of the section a sealed config can be written into after a binary has been built, so
+//! that deployment variants do not need a rebuild each.
+//!
+//! The section is [`SECTION_SIZE`] bytes, starting with [`MAGIC`] followed by the length of the
+//! ciphertext as a little endian `u32`, the nonce, the two key shares that XOR to the
+//! ChaCha20-Poly1305 key, the Ed25519 signature over the canonical form of the config (see
+//! [`BaseConfig::canonical_toml`](crate::BaseConfig::canonical_toml)), and the ciphertext itself.
+//! Until it is patched everything after the magic is [`PLACEHOLDER`].
+
+/// Name of the section in ELF binaries, the only format `nymcfg patch` supports. Mach-O binaries
+/// name it `__DATA,__nymcfg` instead.
+pub const SECTION_NAME: &str = ".nymcfg";
+
+/// Size of the section, which bounds the size of the sealed config.
+pub const SECTION_SIZE: usize = 4096;
+
+/// Marks the start of the section, and the version of its layout.
+pub const MAGIC: [u8; 8] = *b"NYMCFG\x01\0";
+
+/// Every byte of an unpatched section after the magic.
+pub const PLACEHOLDER: u8 = 0xa5;
+
+const LEN_OFFSET: usize = MAGIC.len();
+const NONCE_OFFSET: usize = LEN_OFFSET + 4;
+const SHARE_A_OFFSET: usize = NONCE_OFFSET + 12;
+const SHARE_B_OFFSET: usize = SHARE_A_OFFSET + 32;
+const SIGNATURE_OFFSET: usize = SHARE_B_OFFSET + 32;
+const CIPHERTEXT_OFFSET: usize = SIGNATURE_OFFSET + 64;
+
+/// Largest ciphertext, including the authentication tag, that fits into the section.
+pub const CAPACITY: usize = SECTION_SIZE - CIPHERTEXT_OFFSET;
+
+/// The contents of an unpatched section.
+pub const fn placeholder() -> [u8; SECTION_SIZE] {
+    let mut section = [PLACEHOLDER; SECTION_SIZE];
+    let mut i = 0;
+    while i < MAGIC.len() {
+        section[i] = MAGIC[i];
+        i += 1;
+    }
+    section
+}
+
+/// A sealed and signed config as stored in the section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedConfig {
+    pub nonce: [u8; 12],
+    pub key_share_a: [u8; 32],
+    pub key_share_b: [u8; 32],
+    pub signature: [u8; 64],
+    pub ciphertext: Vec<u8>,
+}
+
+impl SealedConfig {
+    /// Read the sealed config from the contents of the section, `None` if it was never patched.
+    pub fn from_section(section: &[u8]) -> Result<Option<Self>, String> {
+        if section.len() != SECTION_SIZE || section[..LEN_OFFSET] != MAGIC {
+            return Err(format!("not a {SECTION_NAME} section of this version"));
+        }
+        let len = &section[LEN_OFFSET..NONCE_OFFSET];
+        if len.iter().all(|&b| b == PLACEHOLDER) {
+            return Ok(None);
+        }
+
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        if len > CAPACITY {
+            return Err(format!(
+                "sealed config of {len} bytes does not fit into {SECTION_NAME}"
+            ));
+        }
+        let array = |offset: usize, len: usize| &section[offset..offset + len];
+
+        Ok(Some(Self {
+            nonce: array(NONCE_OFFSET, 12).try_into().unwrap(),
+            key_share_a: array(SHARE_A_OFFSET, 32).try_into().unwrap(),
+            key_share_b: array(SHARE_B_OFFSET, 32).try_into().unwrap(),
+            signature: array(SIGNATURE_OFFSET, 64).try_into().unwrap(),
+            ciphertext: array(CIPHERTEXT_OFFSET, len).to_vec(),
+        }))
+    }
+
+    /// The contents of a section holding this sealed config. The unused rest of the section is
+    /// left as [`PLACEHOLDER`].
+    pub fn to_section(&self) -> Result<Vec<u8>, String> {
+        if self.ciphertext.len() > CAPACITY {
+            return Err(format!(
+                "sealed config of {} bytes does not fit into {SECTION_NAME}, at most {CAPACITY} \
+                bytes are available",
+                self.ciphertext.len()
+            ));
+        }
+
+        let mut section = placeholder().to_vec();
+        let len = self.ciphertext.len() as u32;
+        section[LEN_OFFSET..NONCE_OFFSET].copy_from_slice(&len.to_le_bytes());
+        section[NONCE_OFFSET..SHARE_A_OFFSET].copy_from_slice(&self.nonce);
+        section[SHARE_A_OFFSET..SHARE_B_OFFSET].copy_from_slice(&self.key_share_a);
+        section[SHARE_B_OFFSET..SIGNATURE_OFFSET].copy_from_slice(&self.key_share_b);
+        section[SIGNATURE_OFFSET..CIPHERTEXT_OFFSET].copy_from_slice(&self.signature);
+        section[CIPHERTEXT_OFFSET..CIPHERTEXT_OFFSET + self.ciphertext.len()]
+            .copy_from_slice(&self.ciphertext);
+        Ok(section)
+    }
+
+    /// The ChaCha20-Poly1305 key the config is sealed with.
+    pub fn key(&self) -> [u8; 32] {
+        let mut key = self.key_share_a;
+        for (k, b) in key.iter_mut().zip(self.key_share_b) {
+            *k ^= b;
+        }
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(SealedConfig::from_section(&placeholder()), Ok(None));
+        assert!(SealedConfig::from_section(&[0; SECTION_SIZE]).is_err());
+
+        let sealed = SealedConfig {
+            nonce: [1; 12],
+            key_share_a: [2; 32],
+            key_share_b: [3; 32],
+            signature: [4; 64],
+            ciphertext: vec![5; 100],
+        };
+        let section = sealed.to_section().unwrap();
+        assert_eq!(section.len(), SECTION_SIZE);
+        assert_eq!(
+            SealedConfig::from_section(&section),
+            Ok(Some(sealed.clone()))
+        );
+        assert_eq!(sealed.key(), [1; 32]);
+
+        let too_large = SealedConfig {
+            ciphertext: vec![0; CAPACITY + 1],
+            ..sealed
+        };
+        assert!(too_large.to_section().is_err());
+    }
+}
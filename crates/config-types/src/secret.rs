@@ -0,0 +1,106 @@
+//! A wrapper for values that must not leak, such as API keys.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use std::fmt;
+
+/// A secret value, e.g. an API key.
+///
+/// The value is only reachable through [`Secret::expose`]. `Debug` and `Display` print a
+/// placeholder so that it cannot end up in logs, the value is zeroed when it is dropped, and
+/// comparisons take the same time however many bytes match. Only the length of the value can be
+/// told from the time a comparison takes.
+///
+/// It (de)serializes as the value itself, so configs read and write exactly as they would with
+/// the plain value.
+#[derive(Clone, Default)]
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// The secret value. Calls to this are what to audit for leaks.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl<T: Zeroize> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl<T: Zeroize + AsRef<[u8]>> PartialEq for Secret<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_ref().ct_eq(other.0.as_ref()).into()
+    }
+}
+
+impl<T: Zeroize + AsRef<[u8]>> Eq for Secret<T> {}
+
+impl<T: Zeroize + Serialize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Zeroize + Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted() {
+        let secret = Secret::new("hunter2".to_string());
+        assert_eq!(secret.expose(), "hunter2");
+        assert!(!format!("{secret:?} {secret} {:?}", Some(&secret)).contains("hunter2"));
+
+        assert_eq!(secret, Secret::from("hunter2".to_string()));
+        assert_ne!(secret, Secret::from("hunter3".to_string()));
+        assert_ne!(secret, Secret::from("hunter".to_string()));
+    }
+
+    #[test]
+    fn serializes_transparently() {
+        #[derive(Serialize, Deserialize)]
+        struct Keys {
+            key: Secret<String>,
+        }
+
+        let keys: Keys = toml::from_str("key = \"hunter2\"").unwrap();
+        assert_eq!(keys.key.expose(), "hunter2");
+        assert_eq!(toml::to_string(&keys).unwrap(), "key = \"hunter2\"\n");
+
+        let value = toml::Value::try_from(&keys).unwrap();
+        let keys: Keys = value.try_into().unwrap();
+        assert_eq!(keys.key.expose(), "hunter2");
+    }
+}
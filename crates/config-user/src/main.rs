@@ -1,7 +1,21 @@
 fn main() {
+    // lets support check which config a client was built with
+    if std::env::args().any(|arg| arg == "--build-info") {
+        for (name, info) in [
+            ("bootstraps1", bootstraps1::build_info()),
+            ("bootstraps2", bootstraps2::build_info()),
+        ] {
+            println!("{name} {}", info.config_sha256.unwrap_or("none"));
+        }
+        return;
+    }
+
     let config = bootstraps1::Config::default();
-    println!("{}", config.as_ref().ip);
+    println!("{}", config.as_ref().failover_order()[0].host);
 
     let config2 = bootstraps2::Config::default();
-    println!("{}", config2.as_ref().ip);
+    println!("{}", config2.as_ref().failover_order()[0].host);
+
+    let config3 = bootstraps3::Config::default();
+    println!("{}", config3.as_ref().failover_order()[0].host);
 }
@@ -0,0 +1,428 @@
+//! A malformed config must never produce a binary, so make sure it fails the build of the
+//! bootstraps crates rather than the first call to `Config::default()`, and that it does so with
+//! a readable diagnostic rather than a build script panic. The same goes for a missing config
+//! when one is required.
+
+mod common;
+
+use std::path::Path;
+
+/// Secret half of a key standing in for the release key, for tests of release builds, which
+/// refuse the development key.
+const RELEASE_SIGNING_KEY: [u8; 32] = [7; 32];
+
+const MALFORMED_CONFIG: &str = r#"ip = "127.0.0.1"
+
+[keys]
+github = xxxxxxxxxxxxxxxxx
+"#;
+
+/// Build `package` with `features` and `env_var` pointing at `config_path`, returning whether
+/// the build succeeded and its stderr.
+fn build(package: &str, features: &str, env_var: &str, config_path: &Path) -> (bool, String) {
+    let target_dir = common::test_dir(&format!("build-errors-{package}")).join("target");
+    let output = common::cargo_build(&target_dir, package)
+        .args(["--features", features])
+        .env(env_var, config_path)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    (output.status.success(), stderr)
+}
+
+/// Sign the config at `path` with `secret_key`, writing the signature next to it.
+fn sign_config(path: &Path, secret_key: &[u8; 32]) {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    let config: config_types::BaseConfig = std::fs::read_to_string(path).unwrap().parse().unwrap();
+    let signature =
+        SigningKey::from_bytes(secret_key).sign(config.canonical_toml().unwrap().as_bytes());
+
+    let mut sig_path = path.as_os_str().to_owned();
+    sig_path.push(".sig");
+    std::fs::write(sig_path, signature.to_bytes()).unwrap();
+}
+
+/// The public half of `secret_key` as 64 hex digits, the way `NYMVPN_CONFIG_PUBKEY` takes it.
+fn public_key_hex(secret_key: &[u8; 32]) -> String {
+    ed25519_dalek::SigningKey::from_bytes(secret_key)
+        .verifying_key()
+        .to_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// [`build`], expecting the build to fail without a build script panic.
+fn build_failure(package: &str, features: &str, env_var: &str, config_path: &Path) -> String {
+    let (success, stderr) = build(package, features, env_var, config_path);
+
+    assert!(!success, "{package} built with a bad config");
+    assert!(
+        !stderr.contains("panicked"),
+        "build script panicked:\n{stderr}"
+    );
+    stderr
+}
+
+fn malformed_config(package: &str, env_var: &str) {
+    let dir = common::test_dir(&format!("build-errors-{package}"));
+    let config_path = dir.join("malformed-config.toml");
+    std::fs::write(&config_path, MALFORMED_CONFIG).unwrap();
+
+    let stderr = build_failure(package, "", env_var, &config_path);
+
+    let location = format!("{}:4:10 (set by {env_var})", config_path.display());
+    assert!(
+        stderr.contains(&format!(
+            "warning: {package}@0.1.0: invalid bootstrap config {location}"
+        )),
+        "missing cargo warning:\n{stderr}"
+    );
+    assert!(
+        stderr.contains(&format!("error: invalid bootstrap config {location}")),
+        "missing compile error:\n{stderr}"
+    );
+    assert!(
+        stderr.contains("4 | github = xxxxxxxxxxxxxxxxx"),
+        "missing source snippet:\n{stderr}"
+    );
+}
+
+#[test]
+fn malformed_config_fails_bootstraps1_build() {
+    malformed_config("bootstraps1", "NYMVPN_HOSTFILE_CONFIG");
+}
+
+#[test]
+fn malformed_config_fails_bootstraps2_build() {
+    malformed_config("bootstraps2", "NYMVPN_CONFIG_PATH");
+}
+
+/// A missing config falls back to the in-source default with a warning, unless it is required.
+/// Release builds fall back the same way without a signing key, as there is no config to check.
+fn missing_config(package: &str) {
+    let dir = common::test_dir(&format!("build-errors-{package}"));
+    let config_path = dir.join("missing-config.toml");
+    let message = format!(
+        "failed to read bootstrap config {} (set by NYMVPN_CONFIG_PATH)",
+        config_path.display()
+    );
+
+    let (success, stderr) = build(package, "", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(success, "{package} failed to build:\n{stderr}");
+    assert!(
+        stderr.contains(&format!(
+            "warning: {package}@0.1.0: config bootstrapping was enabled, but no config file was found"
+        )) && stderr.contains(&message),
+        "missing cargo warning:\n{stderr}"
+    );
+
+    let target_dir = common::test_dir(&format!("build-errors-{package}")).join("target");
+    let output = common::cargo_build(&target_dir, package)
+        .arg("--release")
+        .env("NYMVPN_CONFIG_PATH", &config_path)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        output.status.success(),
+        "{package} failed to build in release:\n{stderr}"
+    );
+    assert!(
+        stderr.contains(&message),
+        "missing cargo warning:\n{stderr}"
+    );
+
+    let stderr = build_failure(package, "require-cfg", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(
+        stderr.contains(&format!("error: {message}")),
+        "missing compile error:\n{stderr}"
+    );
+}
+
+#[test]
+fn missing_config_bootstraps1() {
+    missing_config("bootstraps1");
+}
+
+#[test]
+fn missing_config_bootstraps2() {
+    missing_config("bootstraps2");
+}
+
+/// A config tracked by git is warned about, or fails the build with `deny-tracked-cfg`.
+fn tracked_config(package: &str) {
+    let dir = common::test_dir(&format!("build-errors-{package}")).join("repo");
+    // start from a fresh repository, a previous run leaves the file ignored and untracked
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let config_path = dir.join("tracked-config.toml");
+    common::write_config(&config_path, "192.0.2.1");
+
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .current_dir(&dir)
+            .args(args)
+            .status();
+        assert!(status.is_ok_and(|status| status.success()), "git {args:?}");
+    };
+    git(&["init", "-q"]);
+    git(&["add", "tracked-config.toml"]);
+
+    let message = format!(
+        "bootstrap config {} is tracked by git",
+        config_path.display()
+    );
+
+    let (success, stderr) = build(package, "", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(success, "{package} failed to build:\n{stderr}");
+    assert!(
+        stderr.contains(&format!("warning: {package}@0.1.0: {message}")),
+        "missing cargo warning:\n{stderr}"
+    );
+
+    let stderr = build_failure(
+        package,
+        "deny-tracked-cfg",
+        "NYMVPN_CONFIG_PATH",
+        &config_path,
+    );
+    assert!(
+        stderr.contains(&format!("error: {message}")),
+        "missing compile error:\n{stderr}"
+    );
+
+    // untracking and ignoring the file fixes the build without any other change
+    git(&["rm", "-q", "--cached", "tracked-config.toml"]);
+    std::fs::write(dir.join(".gitignore"), "tracked-config.toml\n").unwrap();
+    let (success, stderr) = build(
+        package,
+        "deny-tracked-cfg",
+        "NYMVPN_CONFIG_PATH",
+        &config_path,
+    );
+    assert!(success, "{package} failed to build:\n{stderr}");
+}
+
+#[test]
+fn tracked_config_bootstraps1() {
+    tracked_config("bootstraps1");
+}
+
+#[test]
+fn tracked_config_bootstraps2() {
+    tracked_config("bootstraps2");
+}
+
+/// A config whose signature does not match fails the build, an unsigned one only fails release
+/// builds. Release builds also refuse the development key, configs have to be signed with the key
+/// set in `NYMVPN_CONFIG_PUBKEY`.
+fn signatures(package: &str) {
+    let dir = common::test_dir(&format!("build-errors-{package}"));
+    let config_path = dir.join("signed-config.toml");
+    let sig_path = dir.join("signed-config.toml.sig");
+    common::write_config(&config_path, "192.0.2.1");
+    sign_config(&config_path, &config_types::DEV_CONFIG_SIGNING_SECRET);
+
+    let (success, stderr) = build(package, "", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(success, "{package} failed to build:\n{stderr}");
+    assert!(!stderr.contains("is not signed"), "{stderr}");
+
+    common::write_config(&config_path, "192.0.2.2");
+    let stderr = build_failure(package, "", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(
+        stderr.contains(&format!(
+            "error: signature {} does not match bootstrap config {} (set by NYMVPN_CONFIG_PATH)",
+            sig_path.display(),
+            config_path.display()
+        )),
+        "missing compile error:\n{stderr}"
+    );
+
+    std::fs::remove_file(&sig_path).unwrap();
+    let message = format!(
+        "bootstrap config {} (set by NYMVPN_CONFIG_PATH) is not signed",
+        config_path.display()
+    );
+    let (success, stderr) = build(package, "", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(success, "{package} failed to build:\n{stderr}");
+    assert!(
+        stderr.contains(&format!("warning: {package}@0.1.0: {message}")),
+        "missing cargo warning:\n{stderr}"
+    );
+
+    let target_dir = common::test_dir(&format!("build-errors-{package}")).join("target");
+    let release_build = |pubkey: Option<&str>| {
+        let mut cmd = common::cargo_build(&target_dir, package);
+        cmd.arg("--release").env("NYMVPN_CONFIG_PATH", &config_path);
+        if let Some(pubkey) = pubkey {
+            cmd.env("NYMVPN_CONFIG_PUBKEY", pubkey);
+        }
+        let output = cmd.output().unwrap();
+        (
+            output.status.success(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    let (success, stderr) = release_build(None);
+    assert!(
+        !success,
+        "{package} built with the development key in release"
+    );
+    assert!(
+        stderr.contains("error: release builds refuse configs signed with the development key")
+            && stderr.contains("set NYMVPN_CONFIG_PUBKEY"),
+        "missing compile error:\n{stderr}"
+    );
+
+    let release_key = public_key_hex(&RELEASE_SIGNING_KEY);
+    let (success, stderr) = release_build(Some(&release_key));
+    assert!(!success, "{package} built unsigned in release");
+    assert!(
+        stderr.contains(&format!("error: {message}"))
+            && stderr.contains("release builds refuse unsigned configs"),
+        "missing compile error:\n{stderr}"
+    );
+
+    sign_config(&config_path, &RELEASE_SIGNING_KEY);
+    let (success, stderr) = release_build(Some(&release_key));
+    assert!(success, "{package} failed to build in release:\n{stderr}");
+}
+
+#[test]
+fn signatures_bootstraps1() {
+    signatures("bootstraps1");
+}
+
+#[test]
+fn signatures_bootstraps2() {
+    signatures("bootstraps2");
+}
+
+/// A config with a host that is neither an IP address nor a DNS name fails the build.
+fn invalid_host(package: &str) {
+    let dir = common::test_dir(&format!("build-errors-{package}"));
+    let config_path = dir.join("invalid-host-config.toml");
+    common::write_config(&config_path, "not an ip");
+
+    let stderr = build_failure(package, "", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(
+        stderr.contains(&format!(
+            "error: invalid bootstrap config {}:7:8 (set by NYMVPN_CONFIG_PATH)",
+            config_path.display()
+        )) && stderr.contains("invalid host `not an ip`, expected an IP address or a DNS name"),
+        "missing compile error:\n{stderr}"
+    );
+}
+
+#[test]
+fn invalid_host_bootstraps1() {
+    invalid_host("bootstraps1");
+}
+
+#[test]
+fn invalid_host_bootstraps2() {
+    invalid_host("bootstraps2");
+}
+
+#[test]
+fn invalid_host_bootstraps3() {
+    invalid_host("bootstraps3");
+}
+
+/// A config without one of the keys the bootstraps crates require fails the build.
+fn missing_required_key(package: &str) {
+    let dir = common::test_dir(&format!("build-errors-{package}"));
+    let config_path = dir.join("missing-key-config.toml");
+    std::fs::write(
+        &config_path,
+        "version = 1\n\n[keys]\ngitlab = \"gitlab-192.0.2.1\"\n\n[[endpoint]]\nhost = \"192.0.2.1\"\n",
+    )
+    .unwrap();
+
+    let stderr = build_failure(package, "", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(
+        stderr.contains(&format!(
+            "error: invalid bootstrap config {} (set by NYMVPN_CONFIG_PATH)",
+            config_path.display()
+        )) && stderr.contains("missing required keys in [keys]: `github`"),
+        "missing compile error:\n{stderr}"
+    );
+}
+
+#[test]
+fn missing_required_key_bootstraps1() {
+    missing_required_key("bootstraps1");
+}
+
+#[test]
+fn missing_required_key_bootstraps2() {
+    missing_required_key("bootstraps2");
+}
+
+#[test]
+fn missing_required_key_bootstraps3() {
+    missing_required_key("bootstraps3");
+}
+
+/// Unknown fields fail the build under `require-cfg`, with the valid name they are likely a typo
+/// of, and are cargo warnings otherwise.
+fn unknown_fields(package: &str) {
+    let dir = common::test_dir(&format!("build-errors-{package}"));
+    let config_path = dir.join("unknown-fields-config.toml");
+    std::fs::write(
+        &config_path,
+        "version = 1\n\n[keys]\ngithb = \"github-192.0.2.1\"\n\n[[endpoint]]\nhost = \"192.0.2.1\"\n",
+    )
+    .unwrap();
+
+    let stderr = build_failure(package, "require-cfg", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(
+        stderr.contains(&format!(
+            "error: invalid bootstrap config {} (set by NYMVPN_CONFIG_PATH)",
+            config_path.display()
+        )) && stderr.contains("unknown field `keys.githb`, did you mean `github`?"),
+        "missing compile error:\n{stderr}"
+    );
+
+    std::fs::write(
+        &config_path,
+        "version = 1\n\n[keys]\ngithub = \"github-192.0.2.1\"\n\n[[endpoint]]\nhost = \"192.0.2.1\"\nprority = 1\n",
+    )
+    .unwrap();
+    let (success, stderr) = build(package, "", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(success, "{package} failed to build:\n{stderr}");
+    assert!(
+        stderr.contains(&format!(
+            "warning: {package}@0.1.0: bootstrap config {} (set by NYMVPN_CONFIG_PATH): unknown \
+            field `endpoint[0].prority`, did you mean `priority`?",
+            config_path.display()
+        )),
+        "missing cargo warning:\n{stderr}"
+    );
+
+    let stderr = build_failure(package, "strict-cfg", "NYMVPN_CONFIG_PATH", &config_path);
+    assert!(
+        stderr.contains("unknown field `endpoint[0].prority`, did you mean `priority`?"),
+        "missing compile error:\n{stderr}"
+    );
+    let (success, stderr) = build(
+        package,
+        "require-cfg lenient-cfg",
+        "NYMVPN_CONFIG_PATH",
+        &config_path,
+    );
+    assert!(success, "{package} failed to build:\n{stderr}");
+}
+
+#[test]
+fn unknown_fields_bootstraps1() {
+    unknown_fields("bootstraps1");
+}
+
+#[test]
+fn unknown_fields_bootstraps2() {
+    unknown_fields("bootstraps2");
+}
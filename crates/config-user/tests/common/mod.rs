@@ -0,0 +1,89 @@
+//! Helpers shared by the tests that build crates from this workspace with particular configs.
+//! `nymcfg`'s tests include this module by path.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../..")
+        .canonicalize()
+        .unwrap()
+}
+
+/// A scratch directory for a single test, kept under cargo's target directory.
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Clear any config paths or signing keys set in the environment of the test run from `cmd`, so
+/// that tests only see the ones they set themselves.
+pub fn clear_config_env(cmd: &mut Command) -> &mut Command {
+    cmd.env_remove("NYMVPN_CONFIG_PATH")
+        .env_remove("NYMVPN_HOSTFILE_CONFIG")
+        .env_remove("NYMVPN_CONFIG_PUBKEY")
+}
+
+/// A `cargo build` invocation run from the workspace root, building into its own target
+/// directory so that it neither contends with nor pollutes the outer build. The config
+/// environment is cleared, see [`clear_config_env`].
+pub fn cargo_build(target_dir: &Path, package: &str) -> Command {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+
+    let mut cmd = Command::new(cargo);
+    clear_config_env(&mut cmd)
+        .current_dir(workspace_root())
+        .args(["build", "-p", package])
+        .env("CARGO_TARGET_DIR", target_dir);
+    cmd
+}
+
+/// Path of the `config-user` binary built into `target_dir`.
+#[allow(dead_code)] // not every test binary runs what it built
+pub fn config_user_binary(target_dir: &Path) -> PathBuf {
+    target_dir
+        .join("debug")
+        .join(format!("config-user{}", std::env::consts::EXE_SUFFIX))
+}
+
+/// Build `config-user` into `target_dir` with the given environment and return the ips reported
+/// by each of the bootstraps crates.
+#[allow(dead_code)]
+pub fn build_and_run(target_dir: &Path, env: &[(&str, &str)]) -> Vec<String> {
+    let mut cmd = cargo_build(target_dir, "config-user");
+    cmd.envs(env.iter().copied());
+
+    let status = cmd.status().expect("failed to run cargo");
+    assert!(status.success(), "failed to build config-user with {env:?}");
+
+    let output = Command::new(config_user_binary(target_dir))
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Write a config with a single endpoint at `ip` to `path`.
+#[allow(dead_code)]
+pub fn write_config(path: &Path, ip: &str) {
+    let config = format!(
+        "version = 1\n\n[keys]\ngithub = \"github-{ip}\"\n\n[[endpoint]]\nhost = \"{ip}\"\n"
+    );
+    std::fs::write(path, config).unwrap();
+}
+
+/// `path` relative to the workspace root, which is what relative config paths are resolved
+/// against.
+#[allow(dead_code)]
+pub fn workspace_relative(path: &Path) -> String {
+    path.strip_prefix(workspace_root())
+        .expect("path is not inside the workspace")
+        .to_str()
+        .unwrap()
+        .to_string()
+}
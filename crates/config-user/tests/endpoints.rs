@@ -0,0 +1,36 @@
+//! Configs with a list of `[[endpoint]]` entries instead of a single `ip` are embedded by every
+//! bootstraps crate, and clients try the endpoint with the lowest priority first.
+
+mod common;
+
+#[test]
+fn endpoints_are_tried_by_priority() {
+    let dir = common::test_dir("endpoints");
+    let config_path = dir.join("endpoints-config.toml");
+    std::fs::write(
+        &config_path,
+        r#"version = 1
+
+[keys]
+github = "github-endpoints"
+
+[[endpoint]]
+host = "bootstrap.nymvpn.example"
+priority = 1
+region = "eu-west"
+
+[[endpoint]]
+host = "192.0.2.9"
+port = 8443
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        common::build_and_run(
+            &dir.join("target"),
+            &[("NYMVPN_CONFIG_PATH", config_path.to_str().unwrap())]
+        ),
+        ["192.0.2.9"; 3]
+    );
+}
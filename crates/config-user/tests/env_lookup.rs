@@ -0,0 +1,96 @@
+//! The config path is read from the environment when the build scripts run, so changing it between
+//! two builds in the same target directory has to embed the newly named config rather than
+//! whichever one was used when the build scripts themselves were compiled.
+
+mod common;
+
+const DEFAULT_IP: &str = "127.0.0.1";
+
+/// Environment variables set for a build.
+type Env<'a> = &'a [(&'a str, &'a str)];
+
+#[test]
+fn env_changes_are_picked_up() {
+    let dir = common::test_dir("env-lookup");
+    let target_dir = dir.join("target");
+
+    let absolute = dir.join("absolute-config.toml");
+    common::write_config(&absolute, "198.51.100.1");
+
+    let relative = dir.join("relative-config.toml");
+    common::write_config(&relative, "198.51.100.2");
+    let relative = common::workspace_relative(&relative);
+    let relative = relative.as_str();
+
+    let legacy = dir.join("legacy-config.toml");
+    common::write_config(&legacy, "198.51.100.3");
+
+    let absolute = absolute.to_str().unwrap();
+    // `${VAR}` is expanded before relative paths are resolved
+    let absolute_stem = &absolute[..absolute.len() - "-config.toml".len()];
+    let relative_stem = &relative[..relative.len() - "-config.toml".len()];
+    let legacy = legacy.to_str().unwrap();
+
+    // each step is built in the same target directory, so a stale config shows up as the ip from
+    // the step before.
+    let steps: &[(Env, [&str; 3])] = &[
+        (&[("NYMVPN_CONFIG_PATH", absolute)], ["198.51.100.1"; 3]),
+        (&[("NYMVPN_CONFIG_PATH", relative)], ["198.51.100.2"; 3]),
+        (&[("NYMVPN_CONFIG_PATH", absolute)], ["198.51.100.1"; 3]),
+        (&[], [DEFAULT_IP; 3]),
+        (
+            &[
+                ("NYMVPN_CONFIG_PATH", absolute),
+                ("NYMVPN_HOSTFILE_CONFIG", legacy),
+            ],
+            ["198.51.100.1"; 3],
+        ),
+        // only bootstraps1 still reads the deprecated variable
+        (
+            &[("NYMVPN_HOSTFILE_CONFIG", legacy)],
+            ["198.51.100.3", DEFAULT_IP, DEFAULT_IP],
+        ),
+        (&[("NYMVPN_CONFIG_PATH", relative)], ["198.51.100.2"; 3]),
+        // only the interpolated variable changes between these two
+        (
+            &[
+                ("NYMVPN_CONFIG_PATH", "${CONFIG_NAME}-config.toml"),
+                ("CONFIG_NAME", absolute_stem),
+            ],
+            ["198.51.100.1"; 3],
+        ),
+        (
+            &[
+                ("NYMVPN_CONFIG_PATH", "${CONFIG_NAME}-config.toml"),
+                ("CONFIG_NAME", relative_stem),
+            ],
+            ["198.51.100.2"; 3],
+        ),
+    ];
+
+    for (env, expected) in steps {
+        assert_eq!(
+            common::build_and_run(&target_dir, env),
+            expected,
+            "built with {env:?}"
+        );
+    }
+
+    // the deprecated variable loses to the current one, but is not silently dropped
+    let output = common::cargo_build(&target_dir, "config-user")
+        .envs([
+            ("NYMVPN_CONFIG_PATH", absolute),
+            ("NYMVPN_HOSTFILE_CONFIG", legacy),
+        ])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert!(
+        stderr.contains(
+            "warning: bootstraps1@0.1.0: NYMVPN_HOSTFILE_CONFIG is deprecated and ignored, as \
+            NYMVPN_CONFIG_PATH is set"
+        ),
+        "missing deprecation warning:\n{stderr}"
+    );
+}
@@ -0,0 +1,71 @@
+//! Consume bootstraps2 as a path dependency from a workspace that has no `.cargo/config.toml`
+//! setting `CARGO_WORKSPACE_DIR`. The build must still succeed, with relative config paths taken
+//! relative to the workspace bootstraps2 belongs to, as cargo does not tell a build script which
+//! workspace is being built.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn workspace_root_without_cargo_config() {
+    // outside of this workspace, otherwise its .cargo/config.toml would still be picked up
+    let dir = std::env::temp_dir().join(format!("bootstraps-consumer-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+
+    let bootstraps2 = common::workspace_root().join("crates/bootstraps2");
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"consumer\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+            [dependencies]\nbootstraps2 = {{path = {:?}}}\n\n[workspace]\n",
+            bootstraps2.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("src/main.rs"),
+        "fn main() {\n    \
+        let config = bootstraps2::Config::default();\n    \
+        println!(\"{}\", config.as_ref().failover_order()[0].host);\n}\n",
+    )
+    .unwrap();
+    common::write_config(&dir.join("nymvpn-config.toml"), "192.0.2.77");
+    // pin the same dependency versions as this workspace so that the build works offline
+    std::fs::copy(
+        common::workspace_root().join("Cargo.lock"),
+        dir.join("Cargo.lock"),
+    )
+    .unwrap();
+
+    let run = |config_path: Option<&Path>| {
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let mut cmd = Command::new(cargo);
+        cmd.current_dir(&dir)
+            .args(["run", "--quiet", "--offline"])
+            .env("CARGO_TARGET_DIR", common::test_dir("foreign-workspace"))
+            .env_remove("CARGO_WORKSPACE_DIR")
+            .env_remove("NYMVPN_CONFIG_PATH");
+        if let Some(config_path) = config_path {
+            cmd.env("NYMVPN_CONFIG_PATH", config_path);
+        }
+
+        let output = cmd.output().expect("failed to run cargo");
+        assert!(
+            output.status.success(),
+            "consumer failed to build:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    };
+
+    // the default config at the root of this workspace
+    let default_ip = run(None);
+    let consumer_ip = run(Some(&dir.join("nymvpn-config.toml")));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(default_ip, "127.0.0.1");
+    assert_eq!(consumer_ip, "192.0.2.77");
+}
@@ -0,0 +1,45 @@
+//! Config files written for an older layout keep building. Every bootstraps crate migrates them to
+//! the current layout, and the build script based ones say so in a cargo warning so that the file
+//! gets updated.
+
+mod common;
+
+use std::process::Command;
+
+#[test]
+fn old_layout_is_migrated() {
+    let dir = common::test_dir("migrations");
+    let target_dir = dir.join("target");
+    let config_path = dir.join("unversioned-config.toml");
+    std::fs::write(
+        &config_path,
+        "ip = \"192.0.2.11\"\nport = 8443\n\n[keys]\ngithub = \"github-unversioned\"\n",
+    )
+    .unwrap();
+
+    let output = common::cargo_build(&target_dir, "config-user")
+        .env("NYMVPN_CONFIG_PATH", &config_path)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "failed to build:\n{stderr}");
+    for package in ["bootstraps1", "bootstraps2"] {
+        assert!(
+            stderr.contains(&format!(
+                "warning: {package}@0.1.0: migrated bootstrap config {} (set by \
+                NYMVPN_CONFIG_PATH) from version 0 to 1: `ip` and `port` moved into an \
+                `[[endpoint]]` entry",
+                config_path.display()
+            )),
+            "missing migration warning for {package}:\n{stderr}"
+        );
+    }
+
+    let output = Command::new(common::config_user_binary(&target_dir))
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "192.0.2.11\n192.0.2.11\n192.0.2.11\n"
+    );
+}
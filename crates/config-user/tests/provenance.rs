@@ -0,0 +1,81 @@
+//! Every build records which config it embedded in a provenance manifest, and the client reports
+//! the same config hash at runtime, so that support can match a client to the config it shipped.
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+/// Build `config-user` against `config` and return the config hash reported by the binary for
+/// each bootstraps crate along with their provenance manifests.
+fn build(dir: &Path, config: &str) -> Vec<(String, serde_json::Value)> {
+    let target_dir = dir.join("target");
+    let config_path = dir.join("config.toml");
+    std::fs::write(&config_path, config).unwrap();
+
+    let status = common::cargo_build(&target_dir, "config-user")
+        .env("NYMVPN_CONFIG_PATH", &config_path)
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "failed to build config-user");
+
+    let output = Command::new(common::config_user_binary(&target_dir))
+        .arg("--build-info")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    stdout
+        .lines()
+        .map(|line| {
+            let (package, sha256) = line.split_once(' ').unwrap();
+            let manifest_path = target_dir
+                .join("debug")
+                .join(format!("{package}-provenance.json"));
+            let manifest = std::fs::read_to_string(manifest_path).unwrap();
+            assert!(
+                !manifest.contains("github-") && !manifest.contains("192.0.2."),
+                "manifest exposes the config:\n{manifest}"
+            );
+
+            let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+            assert_eq!(manifest["package"], package);
+            assert_eq!(manifest["version"], "0.1.0");
+            assert_eq!(manifest["config"]["sha256"], sha256);
+            assert_eq!(
+                manifest["config"]["path"],
+                config_path.to_str().unwrap(),
+                "{package}"
+            );
+            assert_eq!(manifest["config"]["source"], "set by NYMVPN_CONFIG_PATH");
+            assert!(manifest["features"]
+                .as_array()
+                .unwrap()
+                .contains(&"enable-cfg".into()));
+
+            (sha256.to_string(), manifest)
+        })
+        .collect()
+}
+
+#[test]
+fn provenance_matches_runtime_build_info() {
+    let dir = common::test_dir("provenance");
+
+    let config = "ip = \"192.0.2.5\"\n\n[keys]\ngithub = \"github-a\"\n";
+    let builds = build(&dir, config);
+    assert_eq!(builds.len(), 2);
+    // both crates embed the same config, however differently
+    assert_eq!(builds[0].0, builds[1].0);
+
+    // the hash is of the config, not of how it is written
+    let reformatted = "# comment\n[keys]\ngithub = 'github-a'\n";
+    let reformatted = format!("ip = '192.0.2.5'\n\n{reformatted}");
+    let rebuilt = build(&dir, &reformatted);
+    assert_eq!(rebuilt[0].0, builds[0].0);
+
+    let changed = config.replace("github-a", "github-b");
+    let rebuilt = build(&dir, &changed);
+    assert_ne!(rebuilt[0].0, builds[0].0);
+    assert_eq!(rebuilt[0].0, rebuilt[1].0);
+}
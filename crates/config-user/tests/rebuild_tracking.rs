@@ -0,0 +1,50 @@
+//! Editing the config file has to regenerate the embedded config on the next build, whether the
+//! file is named by an absolute path or by a path relative to the workspace root.
+
+mod common;
+
+use std::path::Path;
+
+fn edit_triggers_rebuild(name: &str, config_path: impl Fn(&Path) -> String) {
+    let dir = common::test_dir(name);
+    let target_dir = dir.join("target");
+
+    let config = dir.join("config.toml");
+    let config_path = config_path(&config);
+    let env = [("NYMVPN_CONFIG_PATH", config_path.as_str())];
+
+    common::write_config(&config, "192.0.2.10");
+    assert_eq!(common::build_and_run(&target_dir, &env), ["192.0.2.10"; 3]);
+
+    // cargo reruns a build script every time if a file it tracks does not exist, so a wrongly
+    // resolved path would pass the check below. Make sure an untouched config is up to date.
+    let output = common::cargo_build(&target_dir, "config-user")
+        .envs(env)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        !stderr.contains("Compiling bootstraps"),
+        "rebuilt without any change to {config_path}:\n{stderr}"
+    );
+
+    // same path, same environment, only the contents change
+    common::write_config(&config, "192.0.2.20");
+    assert_eq!(
+        common::build_and_run(&target_dir, &env),
+        ["192.0.2.20"; 3],
+        "editing {config_path} did not trigger a rebuild"
+    );
+}
+
+#[test]
+fn absolute_config_edit_triggers_rebuild() {
+    edit_triggers_rebuild("rebuild-absolute", |path| {
+        path.to_str().unwrap().to_string()
+    });
+}
+
+#[test]
+fn relative_config_edit_triggers_rebuild() {
+    edit_triggers_rebuild("rebuild-relative", common::workspace_relative);
+}
@@ -0,0 +1,176 @@
+//! Build `config-user` against configs containing known sentinel secrets and make sure that none
+//! of them can be recovered from the binary with something as simple as `strings | grep`.
+//!
+//! Each config variable is given its own build and sentinel config, so that a leak can be
+//! attributed to the crates that read it. Only `bootstraps1` reads the deprecated
+//! `NYMVPN_HOSTFILE_CONFIG`, while `NYMVPN_CONFIG_PATH` is read by every bootstraps crate.
+//!
+//! With `strip-paths` enabled the binary should also be free of any paths from the build host
+//! that point at the embedded config or the code generated for it.
+
+mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The bootstraps crates, in the order `config-user` prints their hosts.
+const CRATES: &[&str] = &["bootstraps1", "bootstraps2", "bootstraps3"];
+
+struct Strategy {
+    /// The crates embedding the config named by `env_var`.
+    names: &'static [&'static str],
+    env_var: &'static str,
+    ip: &'static str,
+    secrets: &'static [&'static str],
+}
+
+const STRATEGIES: &[Strategy] = &[
+    Strategy {
+        names: &["bootstraps1"],
+        env_var: "NYMVPN_HOSTFILE_CONFIG",
+        ip: "198.51.100.23",
+        secrets: &["S1-GITHUB-SENTINEL-7f3a9c", "S1-TRAVIS-SENTINEL-0d52e1"],
+    },
+    Strategy {
+        names: CRATES,
+        env_var: "NYMVPN_CONFIG_PATH",
+        ip: "203.0.113.42",
+        secrets: &["S2-GITHUB-SENTINEL-b81f44", "S2-TRAVIS-SENTINEL-93ac27"],
+    },
+];
+
+impl Strategy {
+    fn config(&self) -> String {
+        format!(
+            "ip = \"{}\"\n\n[keys]\ngithub = \"{}\"\ntravis = \"{}\"\n",
+            self.ip, self.secrets[0], self.secrets[1]
+        )
+    }
+
+    fn sentinels(&self) -> impl Iterator<Item = &'static str> + '_ {
+        std::iter::once(self.ip).chain(self.secrets.iter().copied())
+    }
+}
+
+/// All the encodings of `secret` that we consider to be trivially recoverable.
+fn encodings(secret: &str) -> Vec<(&'static str, Vec<u8>)> {
+    let mut encodings = vec![
+        ("plain text", secret.as_bytes().to_vec()),
+        (
+            "utf-16le",
+            secret.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        ),
+        (
+            "utf-16be",
+            secret.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+        ),
+    ];
+
+    // base64 output depends on where the secret falls relative to the 3 byte input groups, so
+    // check every alignment and only keep the characters that are fully determined by the secret.
+    for offset in 0..3 {
+        let mut input = vec![0u8; offset];
+        input.extend_from_slice(secret.as_bytes());
+        let encoded = STANDARD.encode(&input);
+
+        let start = (offset * 4).div_ceil(3);
+        let end = input.len() * 4 / 3;
+        encodings.push(("base64", encoded.as_bytes()[start..end].to_vec()));
+    }
+
+    encodings
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// Build `config-user` in a dedicated target directory with `strategy` pointed at its sentinel
+/// config and return the path to the resulting binary.
+///
+/// The binary is checked to embed the sentinel config, otherwise an audit would pass trivially
+/// against the in-source defaults.
+fn build_config_user(audit_dir: &Path, strategy: &Strategy, features: &[&str]) -> PathBuf {
+    let target_dir = audit_dir.join("target");
+
+    let mut cmd = common::cargo_build(&target_dir, "config-user");
+    if !features.is_empty() {
+        cmd.args(["--features", &features.join(",")]);
+    }
+
+    let config_path = audit_dir.join(format!("{}.toml", strategy.env_var.to_lowercase()));
+    std::fs::write(&config_path, strategy.config()).unwrap();
+    cmd.env(strategy.env_var, &config_path);
+
+    let status = cmd.status().expect("failed to run cargo");
+    assert!(status.success(), "failed to build config-user");
+
+    let binary = common::config_user_binary(&target_dir);
+    let output = Command::new(&binary).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let hosts: Vec<_> = stdout.lines().collect();
+    assert_eq!(hosts.len(), CRATES.len(), "unexpected output:\n{stdout}");
+    for (name, host) in CRATES.iter().zip(hosts) {
+        if strategy.names.contains(name) {
+            assert_eq!(host, strategy.ip, "{name} did not use the sentinel config");
+        }
+    }
+
+    binary
+}
+
+#[test]
+fn strings_audit() {
+    let audit_dir = common::test_dir("strings-audit");
+
+    let mut leaks = Vec::new();
+    for strategy in STRATEGIES {
+        let binary = build_config_user(&audit_dir, strategy, &[]);
+        let contents = std::fs::read(&binary).unwrap();
+        for sentinel in strategy.sentinels() {
+            for (encoding, needle) in encodings(sentinel) {
+                if contains(&contents, &needle) {
+                    let names = strategy.names.join("/");
+                    leaks.push(format!("{names}: {sentinel:?} ({encoding})"));
+                }
+            }
+        }
+    }
+
+    assert!(
+        leaks.is_empty(),
+        "secrets leaked into the binary:\n{}",
+        leaks.join("\n")
+    );
+}
+
+#[test]
+fn build_paths_audit() {
+    let audit_dir = common::test_dir("paths-audit");
+
+    // the workspace root itself is recorded by rustc as the compilation directory of every
+    // crate in a debug build, which is out of the bootstraps crates' hands.
+    let paths = [
+        audit_dir.to_str().unwrap(),
+        "build/bootstraps1-",
+        "build/bootstraps2-",
+    ];
+    let mut leaks = Vec::new();
+    for strategy in STRATEGIES {
+        let binary = build_config_user(&audit_dir, strategy, &["strip-paths"]);
+        let contents = std::fs::read(&binary).unwrap();
+        leaks.extend(
+            paths
+                .iter()
+                .filter(|path| contains(&contents, path.as_bytes()))
+                .map(|path| format!("{}: {path}", strategy.env_var)),
+        );
+    }
+
+    assert!(
+        leaks.is_empty(),
+        "build paths leaked into the binary:\n{leaks:?}"
+    );
+}
@@ -0,0 +1,59 @@
+//! Finding the config section in ELF binaries.
+
+use config_types::patch::{MAGIC, SECTION_NAME, SECTION_SIZE};
+use object::{Object, ObjectSection};
+
+use std::ops::Range;
+use std::path::Path;
+
+/// Where the config section is in the file at `path`, which is either an ELF binary built with
+/// `patchable-cfg` or a sealed config written by `nymcfg seal`, along with the contents of the
+/// file.
+pub fn find_section(path: &Path) -> Result<(Vec<u8>, Range<usize>), String> {
+    let file =
+        std::fs::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    if file.len() == SECTION_SIZE && file.starts_with(&MAGIC) {
+        return Ok((file, 0..SECTION_SIZE));
+    }
+
+    let (offset, size) = {
+        let binary = object::File::parse(file.as_slice()).map_err(|e| {
+            format!(
+                "{} is neither a sealed config nor an ELF binary: {e}",
+                path.display()
+            )
+        })?;
+        let section = binary.section_by_name(SECTION_NAME).ok_or_else(|| {
+            format!(
+                "{} has no {SECTION_NAME} section, it was not built with `patchable-cfg`",
+                path.display()
+            )
+        })?;
+        section
+            .file_range()
+            .ok_or_else(|| format!("{SECTION_NAME} in {} has no data", path.display()))?
+    };
+    if size != SECTION_SIZE as u64 {
+        return Err(format!(
+            "{SECTION_NAME} in {} is {size} bytes instead of {SECTION_SIZE}",
+            path.display()
+        ));
+    }
+
+    let offset = offset as usize;
+    Ok((file, offset..offset + SECTION_SIZE))
+}
+
+/// Overwrite the config section of the ELF binary at `path` with `section`.
+pub fn patch(path: &Path, section: &[u8]) -> Result<(), String> {
+    let (mut file, range) = find_section(path)?;
+    if range.len() == file.len() {
+        return Err(format!(
+            "{} is a sealed config, not a binary",
+            path.display()
+        ));
+    }
+
+    file[range].copy_from_slice(section);
+    std::fs::write(path, file).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
@@ -0,0 +1,162 @@
+//! Seal, inspect, and patch bootstrap configs for binaries built with the `patchable-cfg` feature
+//! of bootstraps1, which reserves an ELF section a sealed config can be written to after the
+//! build, so that deployment variants do not need a rebuild each. Only ELF binaries can be
+//! patched, the section bootstraps1 reserves on Apple targets is not supported.
+//!
+//! Every config is validated against `config_types::BaseConfig` before it is sealed, so a
+//! malformed config can never end up in a binary.
+
+mod elf;
+mod sealing;
+
+use config_types::patch::{SealedConfig, MAGIC};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage:
+  nymcfg seal --key <signing key> <config.toml> <sealed>
+      validate, sign, and encrypt a config into a sealed config
+  nymcfg patch <sealed> <binary>
+      write a sealed config into a binary built with `patchable-cfg`
+  nymcfg verify [--sha256 <hash>] <binary | sealed>
+      check the signature of the config in a binary, and optionally its hash
+  nymcfg fingerprint <config.toml | sealed | binary>
+      print the SHA-256 of a config without revealing it
+
+signatures are checked against the key in NYMVPN_CONFIG_PUBKEY, the development key if unset";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("seal") => run_seal(&args[1..]),
+        Some("patch") => run_patch(&args[1..]),
+        Some("verify") => run_verify(&args[1..]),
+        Some("fingerprint") => run_fingerprint(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Split `args` into the values of the given `--options` and `N` positional arguments.
+fn parse_args<const N: usize>(
+    args: &[String],
+    options: &[&str],
+) -> Result<(HashMap<String, String>, [PathBuf; N]), String> {
+    let mut values = HashMap::new();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if options.contains(&arg.as_str()) {
+            let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+            values.insert(arg.clone(), value.clone());
+        } else if arg.starts_with("--") {
+            return Err(format!("unknown option {arg}\n{USAGE}"));
+        } else {
+            positional.push(PathBuf::from(arg));
+        }
+    }
+    let positional = positional
+        .try_into()
+        .map_err(|_| format!("expected {N} paths\n{USAGE}"))?;
+    Ok((values, positional))
+}
+
+/// The sealed config in a binary or sealed config file, `None` if the binary was never patched.
+fn read_sealed(path: &Path) -> Result<Option<SealedConfig>, String> {
+    let (file, range) = elf::find_section(path)?;
+    SealedConfig::from_section(&file[range]).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn read_patched(path: &Path) -> Result<SealedConfig, String> {
+    read_sealed(path)?.ok_or_else(|| {
+        format!(
+            "no config was patched into {}, it uses the config it was built with",
+            path.display()
+        )
+    })
+}
+
+/// `nymcfg seal --key <signing key> <config.toml> <sealed>`
+fn run_seal(args: &[String]) -> Result<(), String> {
+    let (options, [config_path, out]) = parse_args(args, &["--key"])?;
+    let key = options
+        .get("--key")
+        .ok_or_else(|| format!("--key is required\n{USAGE}"))?;
+    let config = std::fs::read_to_string(&config_path)
+        .map_err(|e| format!("failed to read config {}: {e}", config_path.display()))?;
+
+    let sealed = sealing::seal(&config, &sealing::read_signing_key(Path::new(key))?)?;
+    std::fs::write(&out, sealed.to_section()?)
+        .map_err(|e| format!("failed to write {}: {e}", out.display()))?;
+
+    println!("sealed {} into {}", config_path.display(), out.display());
+    Ok(())
+}
+
+/// `nymcfg patch <sealed> <binary>`
+fn run_patch(args: &[String]) -> Result<(), String> {
+    let (_, [sealed_path, binary]) = parse_args(args, &[])?;
+
+    // checked up front, the binary would refuse it at runtime
+    let sealed = read_patched(&sealed_path)?;
+    sealing::open_verified(&sealed).map_err(|e| format!("{}: {e}", sealed_path.display()))?;
+    elf::patch(&binary, &sealed.to_section()?)?;
+
+    println!(
+        "patched {} into {}",
+        sealed_path.display(),
+        binary.display()
+    );
+    Ok(())
+}
+
+/// `nymcfg verify [--sha256 <hash>] <binary | sealed>`
+fn run_verify(args: &[String]) -> Result<(), String> {
+    let (options, [path]) = parse_args(args, &["--sha256"])?;
+
+    let config = sealing::open_verified(&read_patched(&path)?)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    let sha256 = sealing::fingerprint(&config)?;
+    if let Some(expected) = options.get("--sha256") {
+        if !expected.eq_ignore_ascii_case(&sha256) {
+            return Err(format!(
+                "config in {} has SHA-256 {sha256}, expected {expected}",
+                path.display()
+            ));
+        }
+    }
+
+    println!("{}: signature ok, sha256 {sha256}", path.display());
+    Ok(())
+}
+
+/// `nymcfg fingerprint <config.toml | sealed | binary>`
+fn run_fingerprint(args: &[String]) -> Result<(), String> {
+    let (_, [path]) = parse_args(args, &[])?;
+
+    let file =
+        std::fs::read(&path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    let config = if file.starts_with(&MAGIC) || file.starts_with(b"\x7fELF") {
+        sealing::open(&read_patched(&path)?)?
+    } else {
+        let config = String::from_utf8(file).map_err(|_| {
+            format!(
+                "{} is not a config, sealed config, or binary",
+                path.display()
+            )
+        })?;
+        sealing::validate(&config)?
+    };
+
+    println!("{}", sealing::fingerprint(&config)?);
+    Ok(())
+}
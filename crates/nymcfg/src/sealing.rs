@@ -0,0 +1,176 @@
+//! Sealing configs into the layout of `config_types::patch` and opening them again.
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use config_types::{patch::SealedConfig, BaseConfig};
+use ed25519_dalek::{Signer, SigningKey};
+
+use std::path::Path;
+
+/// Read an Ed25519 secret key stored as its 32 raw bytes.
+pub fn read_signing_key(path: &Path) -> Result<SigningKey, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("failed to read signing key {}: {e}", path.display()))?;
+    let bytes = <[u8; 32]>::try_from(bytes.as_slice()).map_err(|_| {
+        format!(
+            "signing key {} is not 32 bytes of an Ed25519 secret key",
+            path.display()
+        )
+    })?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Parse `config` as a [`BaseConfig`] and check that it has every required key and no unknown
+/// fields, so that a malformed config is never sealed.
+pub fn validate(contents: &str) -> Result<BaseConfig, String> {
+    let config: BaseConfig = contents
+        .parse()
+        .map_err(|e| format!("invalid bootstrap config: {e}"))?;
+    if let Some(field) =
+        config_types::unknown_fields(contents, config_types::REQUIRED_KEYS)?.first()
+    {
+        return Err(format!("invalid bootstrap config: {field}"));
+    }
+    config
+        .keys
+        .require(config_types::REQUIRED_KEYS)
+        .map_err(|e| format!("invalid bootstrap config: {e}"))?;
+    Ok(config)
+}
+
+/// Validate `config`, sign its canonical form with `key`, and seal it under a freshly drawn key
+/// stored as two shares, like the build scripts do.
+pub fn seal(config: &str, key: &SigningKey) -> Result<SealedConfig, String> {
+    let canonical = validate(config)?
+        .canonical_toml()
+        .map_err(|e| e.to_string())?;
+    let signature = key.sign(canonical.as_bytes());
+
+    let cipher_key = ChaCha20Poly1305::generate_key(&mut OsRng);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let key_share_a: [u8; 32] = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+    let mut key_share_b = key_share_a;
+    for (b, k) in key_share_b.iter_mut().zip(cipher_key) {
+        *b ^= k;
+    }
+
+    let ciphertext = ChaCha20Poly1305::new(&cipher_key)
+        .encrypt(&nonce, config.as_bytes())
+        .map_err(|_| "failed to seal bootstrap config".to_string())?;
+
+    Ok(SealedConfig {
+        nonce: nonce.into(),
+        key_share_a,
+        key_share_b,
+        signature: signature.to_bytes(),
+        ciphertext,
+    })
+}
+
+/// Decrypt and parse a sealed config, without checking its signature.
+pub fn open(sealed: &SealedConfig) -> Result<BaseConfig, String> {
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(&sealed.key()))
+        .decrypt(
+            Nonce::from_slice(&sealed.nonce),
+            sealed.ciphertext.as_slice(),
+        )
+        .map_err(|_| "failed to open sealed config, it is corrupt".to_string())?;
+    let config =
+        String::from_utf8(plaintext).map_err(|_| "sealed config is not valid utf-8".to_string())?;
+    validate(&config)
+}
+
+/// Name of the environment variable holding the key configs are signed with, the same one the
+/// bootstraps build scripts read.
+const PUBKEY_ENV: &str = "NYMVPN_CONFIG_PUBKEY";
+
+/// The key configs are signed with, from [`PUBKEY_ENV`] as 64 hex digits, or the development key
+/// if it is not set, the same key a binary built in the same environment checks configs against.
+pub fn verifying_key() -> Result<[u8; 32], String> {
+    match std::env::var(PUBKEY_ENV) {
+        Ok(hex) => config_types::verifying_key_from_hex(&hex)
+            .map_err(|e| format!("invalid config signing key in {PUBKEY_ENV}: {e}")),
+        Err(_) => Ok(config_types::DEV_CONFIG_SIGNING_KEY),
+    }
+}
+
+/// Open a sealed config and check its signature against [`verifying_key`], the way bootstraps1
+/// does at runtime.
+pub fn open_verified(sealed: &SealedConfig) -> Result<BaseConfig, String> {
+    let config = open(sealed)?;
+    config
+        .verify_signature(&verifying_key()?, &sealed.signature)
+        .map_err(|_| {
+            "sealed config does not match its signature, it was not signed with the config signing \
+        key"
+            .to_string()
+        })?;
+    Ok(config)
+}
+
+/// SHA-256 of the canonical form of `config`, hex encoded. This is the hash recorded in the
+/// provenance manifest by the build scripts and reported by `build_info()`.
+pub fn fingerprint(config: &BaseConfig) -> Result<String, String> {
+    config_types::canonical_sha256(config).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "ip = \"192.0.2.1\"\n\n[keys]\ngithub = \"sealed\"\n";
+
+    #[test]
+    fn seal_and_open() {
+        let sealed = seal(CONFIG, &SigningKey::from_bytes(&[7; 32])).unwrap();
+        assert!(!sealed
+            .ciphertext
+            .windows(6)
+            .any(|window| window == b"sealed"));
+
+        let config = open(&sealed).unwrap();
+        assert_eq!(config.endpoints[0].host.to_string(), "192.0.2.1");
+        // signed with a key other than the config signing key
+        assert!(open_verified(&sealed).is_err());
+
+        let mut corrupt = sealed.clone();
+        corrupt.ciphertext[0] ^= 1;
+        assert!(open(&corrupt).is_err());
+
+        assert!(seal("ip = 192.0.2.1", &SigningKey::from_bytes(&[7; 32])).is_err());
+        let err = seal(
+            &CONFIG.replace("github", "gitlab"),
+            &SigningKey::from_bytes(&[7; 32]),
+        )
+        .err()
+        .unwrap();
+        assert!(
+            err.contains("missing required keys in [keys]: `github`"),
+            "{err}"
+        );
+        let err = seal(
+            &CONFIG.replace("github", "githb"),
+            &SigningKey::from_bytes(&[7; 32]),
+        )
+        .err()
+        .unwrap();
+        assert!(
+            err.contains("unknown field `keys.githb`, did you mean `github`?"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn fingerprint_ignores_formatting() {
+        let a = validate(CONFIG).unwrap();
+        let b = validate("ip = '192.0.2.1' # comment\n[keys]\ngithub = 'sealed'\n").unwrap();
+        let c = validate("ip = '192.0.2.2'\n[keys]\ngithub = 'sealed'\n").unwrap();
+
+        let hash = fingerprint(&a).unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, fingerprint(&b).unwrap());
+        assert_ne!(hash, fingerprint(&c).unwrap());
+    }
+}
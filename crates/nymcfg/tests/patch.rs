@@ -0,0 +1,200 @@
+//! Seal a config, patch it into a config-user binary built with `patchable-cfg`, and check that
+//! bootstraps1 picks it up without a rebuild, and that `nymcfg` reports on it.
+
+#[path = "../../config-user/tests/common/mod.rs"]
+mod common;
+
+use config_types::DEV_CONFIG_SIGNING_SECRET;
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn test_dir() -> PathBuf {
+    common::test_dir("nymcfg-patch")
+}
+
+/// Build config-user with `patchable-cfg` from the example config in the workspace root.
+fn build_config_user(target_dir: &Path) -> PathBuf {
+    let status = common::cargo_build(target_dir, "config-user")
+        .args(["--features", "patchable-cfg"])
+        .status()
+        .unwrap();
+    assert!(status.success(), "failed to build config-user");
+
+    common::config_user_binary(target_dir)
+}
+
+/// Run `nymcfg`, which checks signatures against the key in `NYMVPN_CONFIG_PUBKEY` when it is
+/// set, so the environment of the test run is cleared like for the build.
+fn nymcfg(args: &[&OsStr]) -> Output {
+    common::clear_config_env(&mut Command::new(env!("CARGO_BIN_EXE_nymcfg")))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Run `nymcfg`, expecting it to fail with an error containing `message`.
+fn nymcfg_failure(args: &[&OsStr], message: &str) {
+    let output = nymcfg(args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "nymcfg {args:?} succeeded");
+    assert!(stderr.contains(message), "{stderr}");
+}
+
+fn run(binary: &Path) -> Output {
+    Command::new(binary).output().unwrap()
+}
+
+fn stdout_lines(output: &Output) -> Vec<String> {
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Seal `config` into `sealed` with the given signing key.
+fn seal(key: [u8; 32], config: &Path, sealed: &Path) {
+    let key_path = sealed.with_extension("key");
+    std::fs::write(&key_path, key).unwrap();
+    let output = nymcfg(&[
+        "seal".as_ref(),
+        "--key".as_ref(),
+        key_path.as_ref(),
+        config.as_ref(),
+        sealed.as_ref(),
+    ]);
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn patch_built_binary() {
+    let dir = test_dir();
+    let built = build_config_user(&dir.join("target"));
+    let binary = dir.join("config-user");
+    std::fs::copy(&built, &binary).unwrap();
+
+    // the section is left alone until patched, so the embedded config is used
+    assert_eq!(stdout_lines(&run(&binary)), ["127.0.0.1"; 3]);
+    nymcfg_failure(
+        &["verify".as_ref(), binary.as_ref()],
+        "no config was patched into",
+    );
+
+    let config = dir.join("patched-config.toml");
+    std::fs::write(
+        &config,
+        "ip = \"192.0.2.7\"\n\n[keys]\ngithub = \"patched\"\n",
+    )
+    .unwrap();
+    let sealed = dir.join("patched-config.sealed");
+    seal(DEV_CONFIG_SIGNING_SECRET, &config, &sealed);
+
+    let fingerprint = stdout_lines(&nymcfg(&["fingerprint".as_ref(), config.as_ref()]));
+    assert_eq!(fingerprint[0].len(), 64);
+    assert_eq!(
+        stdout_lines(&nymcfg(&["fingerprint".as_ref(), sealed.as_ref()])),
+        fingerprint
+    );
+    // the fingerprint does not give the config away
+    assert!(!std::fs::read(&sealed)
+        .unwrap()
+        .windows(9)
+        .any(|window| window == b"192.0.2.7"));
+
+    let output = nymcfg(&["patch".as_ref(), sealed.as_ref(), binary.as_ref()]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        stdout_lines(&run(&binary)),
+        ["192.0.2.7", "127.0.0.1", "127.0.0.1"]
+    );
+
+    let output = nymcfg(&[
+        "verify".as_ref(),
+        "--sha256".as_ref(),
+        fingerprint[0].as_ref(),
+        binary.as_ref(),
+    ]);
+    let expected = format!("signature ok, sha256 {}", fingerprint[0]);
+    assert!(stdout_lines(&output)[0].ends_with(&expected));
+    assert_eq!(
+        stdout_lines(&nymcfg(&["fingerprint".as_ref(), binary.as_ref()])),
+        fingerprint
+    );
+    nymcfg_failure(
+        &[
+            "verify".as_ref(),
+            "--sha256".as_ref(),
+            "00".as_ref(),
+            binary.as_ref(),
+        ],
+        &format!("has SHA-256 {}, expected 00", fingerprint[0]),
+    );
+
+    // a config signed with any other key is refused by the tool, and at runtime
+    let forged = dir.join("forged-config.sealed");
+    seal([7; 32], &config, &forged);
+    nymcfg_failure(
+        &["patch".as_ref(), forged.as_ref(), binary.as_ref()],
+        "does not match its signature",
+    );
+    nymcfg_failure(
+        &["verify".as_ref(), forged.as_ref()],
+        "does not match its signature",
+    );
+
+    let mut patched = std::fs::read(&binary).unwrap();
+    let section = std::fs::read(&sealed).unwrap();
+    let offset = patched
+        .windows(section.len())
+        .position(|window| window == section)
+        .unwrap();
+    patched[offset..offset + section.len()].copy_from_slice(&std::fs::read(&forged).unwrap());
+    std::fs::write(&binary, patched).unwrap();
+    let output = run(&binary);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not match its signature"));
+}
+
+#[test]
+fn malformed_config_is_not_sealed() {
+    let dir = test_dir();
+    let config = dir.join("malformed-config.toml");
+    std::fs::write(&config, "ip = 192.0.2.7\n").unwrap();
+    let key = dir.join("malformed-config.key");
+    std::fs::write(&key, DEV_CONFIG_SIGNING_SECRET).unwrap();
+    let sealed = dir.join("malformed-config.sealed");
+
+    nymcfg_failure(
+        &[
+            "seal".as_ref(),
+            "--key".as_ref(),
+            key.as_ref(),
+            config.as_ref(),
+            sealed.as_ref(),
+        ],
+        "invalid bootstrap config",
+    );
+    assert!(!sealed.exists());
+    nymcfg_failure(
+        &["fingerprint".as_ref(), config.as_ref()],
+        "invalid bootstrap config",
+    );
+}
+
+#[test]
+fn binary_without_section() {
+    let dir = test_dir();
+    let config = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../nymvpn-config.toml");
+    let sealed = dir.join("example-config.sealed");
+    seal(DEV_CONFIG_SIGNING_SECRET, &config, &sealed);
+    let binary = dir.join("nymcfg-copy");
+    std::fs::copy(env!("CARGO_BIN_EXE_nymcfg"), &binary).unwrap();
+
+    nymcfg_failure(
+        &["patch".as_ref(), sealed.as_ref(), binary.as_ref()],
+        "has no .nymcfg section",
+    );
+}